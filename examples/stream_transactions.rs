@@ -0,0 +1,48 @@
+use solana_grpc::{
+    proto::CommitmentLevel, transaction_signature, transaction_slot, transaction_succeeded, GrpcStreamManager,
+    SubscribeRequestBuilder, TransactionFilterBuilder,
+};
+
+/// Streams the transactions of a program and prints their signatures
+/// 
+/// Configured through the `GRPC_ENDPOINT`, `GRPC_X_TOKEN` and `PROGRAM_ID` environment
+/// variables, stops on Ctrl-C.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let endpoint = std::env::var("GRPC_ENDPOINT").unwrap_or_else(|_| "http://127.0.0.1:10000".to_string());
+    let x_token = std::env::var("GRPC_X_TOKEN").ok();
+    // Defaults to the SPL token program
+    let program = std::env::var("PROGRAM_ID").unwrap_or_else(|_| "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string());
+
+    let mut manager = GrpcStreamManager::new(
+        &endpoint,
+        x_token,
+        Box::new(|tx, endpoint| {
+            let signature = transaction_signature(&tx).unwrap_or_default();
+            let status = if transaction_succeeded(&tx) { "ok" } else { "failed" };
+            println!("[{}] slot {} {} {}", endpoint, transaction_slot(&tx), signature, status);
+        }),
+    )
+    .await?;
+
+    let handle = manager.handle();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            handle.shutdown();
+        }
+    });
+
+    let filter = TransactionFilterBuilder::new()
+        .include_any_account([program])
+        .vote(false)
+        .build();
+    let request = SubscribeRequestBuilder::new()
+        .transactions("program", filter)
+        .commitment(CommitmentLevel::Confirmed)
+        .build();
+
+    manager.connect(request).await?;
+    Ok(())
+}
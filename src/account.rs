@@ -0,0 +1,44 @@
+use yellowstone_grpc_proto::geyser::SubscribeUpdateAccount;
+
+/// The fields of an account update with addresses encoded as base58
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodedAccount {
+    /// The address of the account
+    pub pubkey: String,
+    /// The program owning the account
+    pub owner: String,
+    /// Balance in lamports
+    pub lamports: u64,
+    /// Whether the account holds a loaded program
+    pub executable: bool,
+    /// The epoch at which rent is next due
+    pub rent_epoch: u64,
+    /// The raw account data
+    pub data: Vec<u8>,
+    /// Orders writes to the account, higher is more recent
+    pub write_version: u64,
+    /// The slot the update was observed in
+    pub slot: u64,
+}
+
+impl DecodedAccount {
+    /// Extracts the account from an account update
+    /// 
+    /// Returns `None` for updates without account info.
+    /// 
+    /// # Arguments
+    /// * `update` - The account update
+    pub fn from_update(update: &SubscribeUpdateAccount) -> Option<Self> {
+        let info = update.account.as_ref()?;
+        Some(Self {
+            pubkey: bs58::encode(&info.pubkey).into_string(),
+            owner: bs58::encode(&info.owner).into_string(),
+            lamports: info.lamports,
+            executable: info.executable,
+            rent_epoch: info.rent_epoch,
+            data: info.data.clone(),
+            write_version: info.write_version,
+            slot: update.slot,
+        })
+    }
+}
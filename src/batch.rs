@@ -0,0 +1,78 @@
+use {
+    std::time::Duration,
+    tokio::time::Instant,
+    yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof,
+};
+
+/// Limits controlling when buffered updates are flushed
+#[derive(Debug, Clone, Copy)]
+pub struct BatchConfig {
+    /// Number of updates that triggers a flush
+    pub batch_size: usize,
+    /// Maximum time the first buffered update waits before a flush
+    pub batch_timeout: Duration,
+}
+
+/// Buffers updates and hands them to the batch handler
+pub(crate) struct Batcher {
+    config: BatchConfig,
+    handler: Box<dyn Fn(Vec<UpdateOneof>, &str) + Send + Sync>,
+    pending: Vec<UpdateOneof>,
+    deadline: Option<Instant>,
+}
+
+impl Batcher {
+    pub(crate) fn new(config: BatchConfig, handler: Box<dyn Fn(Vec<UpdateOneof>, &str) + Send + Sync>) -> Self {
+        Batcher {
+            config,
+            handler,
+            pending: Vec::with_capacity(config.batch_size),
+            deadline: None,
+        }
+    }
+
+    /// Returns when the pending batch has to be flushed, `None` if it is empty
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Buffers an update, flushing if the batch is full
+    /// 
+    /// # Arguments
+    /// * `update` - The update to buffer
+    /// * `endpoint` - The endpoint passed to the handler on flush
+    pub(crate) fn push(&mut self, update: UpdateOneof, endpoint: &str) {
+        if self.pending.is_empty() {
+            self.deadline = Some(Instant::now() + self.config.batch_timeout);
+        }
+        self.pending.push(update);
+
+        if self.pending.len() >= self.config.batch_size {
+            self.flush(endpoint);
+        }
+    }
+
+    /// Flushes the pending batch if its timeout has passed
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The endpoint passed to the handler
+    pub(crate) fn flush_expired(&mut self, endpoint: &str) {
+        if self.deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            self.flush(endpoint);
+        }
+    }
+
+    /// Hands all pending updates to the handler
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The endpoint passed to the handler
+    pub(crate) fn flush(&mut self, endpoint: &str) {
+        self.deadline = None;
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let batch = std::mem::replace(&mut self.pending, Vec::with_capacity(self.config.batch_size));
+        (self.handler)(batch, endpoint);
+    }
+}
@@ -0,0 +1,236 @@
+use {
+    std::{
+        fs::{self, File},
+        io::{self, ErrorKind, Write},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    },
+    yellowstone_grpc_proto::geyser::{subscribe_update::UpdateOneof, SubscribeUpdateAccount, SubscribeUpdateTransaction},
+};
+
+/// How far the handlers got through the stream
+/// 
+/// Positions order by slot first, then by the position within the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct StreamPosition {
+    /// The slot of the last handled update
+    pub slot: u64,
+    /// The transaction index or account write version within the slot, `None` for
+    /// block and slot updates
+    pub within_slot: Option<u64>,
+}
+
+impl StreamPosition {
+    /// Returns the position of an update, `None` for updates without a slot
+    /// 
+    /// # Arguments
+    /// * `update` - The update to locate
+    pub(crate) fn of(update: &UpdateOneof) -> Option<Self> {
+        match update {
+            UpdateOneof::Transaction(tx) => Some(Self::of_transaction(tx)),
+            UpdateOneof::Account(account) => Some(Self::of_account(account)),
+            UpdateOneof::Block(block) => Some(StreamPosition { slot: block.slot, within_slot: None }),
+            UpdateOneof::Slot(slot) => Some(StreamPosition { slot: slot.slot, within_slot: None }),
+            _ => None,
+        }
+    }
+
+    /// Returns the position of a transaction update
+    /// 
+    /// # Arguments
+    /// * `tx` - The transaction update to locate
+    pub(crate) fn of_transaction(tx: &SubscribeUpdateTransaction) -> Self {
+        StreamPosition {
+            slot: tx.slot,
+            within_slot: tx.transaction.as_ref().map(|info| info.index),
+        }
+    }
+
+    /// Returns the position of an account update
+    /// 
+    /// # Arguments
+    /// * `account` - The account update to locate
+    pub(crate) fn of_account(account: &SubscribeUpdateAccount) -> Self {
+        StreamPosition {
+            slot: account.slot,
+            within_slot: account.account.as_ref().map(|info| info.write_version),
+        }
+    }
+}
+
+/// Durable store of the stream position, see `GrpcStreamManager::set_bookmark`
+pub trait Bookmark: Send + Sync {
+    /// Records that the updates up to `position` were handled
+    /// 
+    /// # Arguments
+    /// * `position` - The position to store, lower than the stored one only after `connect` rewound with an explicit `from_slot`
+    fn commit(&mut self, position: StreamPosition) -> io::Result<()>;
+
+    /// Returns the last committed position, `None` if nothing was committed yet
+    fn load(&mut self) -> io::Result<Option<StreamPosition>>;
+}
+
+/// Keeps the position in a file, surviving restarts
+/// 
+/// Each commit writes the position to a temporary file next to `path`, named after it
+/// with `.tmp` appended, syncs it and renames it over `path`, so a crash leaves either
+/// the previous or the new position.
+#[derive(Debug, Clone)]
+pub struct FileBookmark {
+    path: PathBuf,
+}
+
+impl FileBookmark {
+    /// Creates a bookmark stored at `path`, which is created on the first commit
+    /// 
+    /// # Arguments
+    /// * `path` - Where the position is stored
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        FileBookmark {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl Bookmark for FileBookmark {
+    fn commit(&mut self, position: StreamPosition) -> io::Result<()> {
+        let mut staged = self.path.clone().into_os_string();
+        staged.push(".tmp");
+        let staged = PathBuf::from(staged);
+        let mut file = File::create(&staged)?;
+        match position.within_slot {
+            Some(within_slot) => writeln!(file, "{} {}", position.slot, within_slot)?,
+            None => writeln!(file, "{}", position.slot)?,
+        }
+        file.sync_all()?;
+        fs::rename(&staged, &self.path)
+    }
+
+    fn load(&mut self) -> io::Result<Option<StreamPosition>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let invalid = || io::Error::new(ErrorKind::InvalidData, format!("malformed bookmark {:?}", contents.trim()));
+        let mut fields = contents.split_whitespace().map(str::parse::<u64>);
+        let slot = fields.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+        let within_slot = fields.next().transpose().map_err(|_| invalid())?;
+        Ok(Some(StreamPosition { slot, within_slot }))
+    }
+}
+
+/// Keeps the position in memory, e.g. for tests or to resume within one process
+/// 
+/// Clones share the position, so a clone kept outside the manager observes its commits.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBookmark {
+    position: Arc<Mutex<Option<StreamPosition>>>,
+}
+
+impl MemoryBookmark {
+    /// Creates a bookmark without a committed position
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the last committed position
+    pub fn position(&self) -> Option<StreamPosition> {
+        *self.position.lock().unwrap()
+    }
+}
+
+impl Bookmark for MemoryBookmark {
+    fn commit(&mut self, position: StreamPosition) -> io::Result<()> {
+        *self.position.lock().unwrap() = Some(position);
+        Ok(())
+    }
+
+    fn load(&mut self) -> io::Result<Option<StreamPosition>> {
+        Ok(self.position())
+    }
+}
+
+/// Tracks handled updates and commits the highest position to a [`Bookmark`]
+pub(crate) struct Bookmarking {
+    store: Box<dyn Bookmark>,
+    commit_every: u32,
+    pending: Option<StreamPosition>,
+    uncommitted: u32,
+    committed: Option<StreamPosition>,
+}
+
+impl Bookmarking {
+    /// Wraps `store`, committing after every `commit_every` handled updates
+    /// 
+    /// # Arguments
+    /// * `store` - Where positions are committed
+    /// * `commit_every` - Number of handled updates between commits
+    pub(crate) fn new(store: Box<dyn Bookmark>, commit_every: u32) -> Self {
+        Bookmarking {
+            store,
+            commit_every: commit_every.max(1),
+            pending: None,
+            uncommitted: 0,
+            committed: None,
+        }
+    }
+
+    /// Prepares a new `connect`, returning the slot to subscribe from
+    /// 
+    /// An explicit `from_slot` takes precedence over the stored position, which is then
+    /// not loaded, so later commits may move it backwards.
+    /// 
+    /// # Arguments
+    /// * `from_slot` - The `from_slot` of the request passed to `connect`
+    pub(crate) fn start(&mut self, from_slot: Option<u64>) -> io::Result<Option<u64>> {
+        self.committed = match from_slot {
+            Some(_) => None,
+            None => self.store.load()?,
+        };
+        Ok(from_slot.or(self.resume_slot()))
+    }
+
+    /// Records a handled update, committing once `commit_every` updates are pending
+    /// 
+    /// # Arguments
+    /// * `position` - The position of the handled update
+    /// * `hold` - Whether to defer the commit, e.g. while earlier updates wait for a retry
+    pub(crate) fn advance(&mut self, position: StreamPosition, hold: bool) -> io::Result<()> {
+        self.pending = self.pending.max(Some(position));
+        self.uncommitted += 1;
+        if self.uncommitted >= self.commit_every && !hold {
+            return self.commit();
+        }
+        Ok(())
+    }
+
+    /// Forgets the handled positions not committed yet, so they are delivered again on resume
+    pub(crate) fn discard(&mut self) {
+        self.pending = None;
+        self.uncommitted = 0;
+    }
+
+    /// Commits the highest pending position if it is ahead of the committed one
+    pub(crate) fn commit(&mut self) -> io::Result<()> {
+        self.uncommitted = 0;
+        let Some(position) = self.pending.take() else {
+            return Ok(());
+        };
+        if Some(position) <= self.committed {
+            return Ok(());
+        }
+        if let Err(err) = self.store.commit(position) {
+            // Kept for the next commit, the store still holds an earlier position
+            self.pending = Some(position);
+            return Err(err);
+        }
+        self.committed = Some(position);
+        Ok(())
+    }
+
+    /// Returns the slot a resubscribe resumes from
+    pub(crate) fn resume_slot(&self) -> Option<u64> {
+        self.committed.map(|position| position.slot)
+    }
+}
@@ -0,0 +1,96 @@
+use {
+    crate::BackoffStrategy,
+    std::{net::IpAddr, time::Duration},
+    tonic::codec::CompressionEncoding,
+};
+
+#[cfg(feature = "proxy")]
+use tonic::transport::Uri;
+
+/// Settings applied when building the gRPC channel
+/// 
+/// No timeout is set on the tonic `Endpoint` itself. An endpoint-level timeout applies to
+/// every request on the channel, including the long-lived subscribe stream, and depending
+/// on the tonic version and any proxies in between it can abort that stream or its
+/// establishment. Unary calls and the subscribe RPC are instead bounded individually by
+/// `request_timeout` and `subscribe_timeout`.
+/// 
+/// The channel always uses native HTTP/2. gRPC-Web is not offered as a transport: the
+/// geyser `Subscribe` RPC is bidirectional, with pongs and subscription updates sent on
+/// the request stream, while gRPC-Web only carries unary and server-streaming calls.
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+    /// Timeout for establishing the TCP/TLS connection
+    pub connect_timeout: Duration,
+    /// Number of times a failed initial connect is retried before the constructor fails
+    pub connect_retries: u32,
+    /// How the delay between initial connect attempts grows
+    /// 
+    /// Delays are computed as for stream reconnects, by default growing linearly by one
+    /// second per attempt up to five seconds.
+    pub connect_backoff: BackoffStrategy,
+    /// Timeout applied to unary calls such as health checks
+    pub request_timeout: Duration,
+    /// Timeout for the server to accept the subscribe RPC, `None` to wait indefinitely
+    /// 
+    /// This only bounds the wait until the stream is established; once it is, updates
+    /// are received for as long as the stream stays open.
+    pub subscribe_timeout: Option<Duration>,
+    /// Whether the server should replay a snapshot of matching accounts on every subscribe
+    pub request_snapshot: bool,
+    /// Local address outgoing connections are bound to, `None` to let the OS choose
+    /// 
+    /// On multi-homed hosts this selects the interface connections originate from.
+    pub local_address: Option<IpAddr>,
+    /// Compression the server may apply to streamed updates, `None` for uncompressed
+    /// 
+    /// tonic negotiates compression per channel and does not expose compression levels,
+    /// so the encoding applies to every update type and the level is chosen by the server.
+    /// Large block and account updates compress well, while small high-frequency
+    /// transaction updates mostly add decompression overhead; on bandwidth-constrained
+    /// links zstd is usually the better tradeoff, gzip is more widely supported.
+    pub accept_compression: Option<CompressionEncoding>,
+    /// Proxy the channel connects through, `None` to use the proxy environment variables
+    /// 
+    /// `http://` proxies tunnel with `CONNECT`, `socks5://` and `socks5h://` proxies use
+    /// SOCKS5 with the endpoint resolved locally or by the proxy. Credentials are taken
+    /// from the URI's user info. Without an explicit proxy, `HTTPS_PROXY` applies to
+    /// `https` endpoints and `HTTP_PROXY` to `http` ones, both falling back to `ALL_PROXY`,
+    /// and hosts listed in `NO_PROXY` are connected to directly. Lowercase variable names
+    /// take precedence over uppercase ones. Proxies given without a scheme, such as
+    /// `proxy.corp:3128`, are HTTP proxies.
+    #[cfg(feature = "proxy")]
+    pub proxy: Option<Uri>,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfig {
+            connect_timeout: Duration::from_secs(10),
+            connect_retries: 0,
+            connect_backoff: BackoffStrategy::Linear {
+                interval: Duration::from_secs(1),
+                max_multiplier: 5,
+            },
+            request_timeout: Duration::from_secs(10),
+            subscribe_timeout: Some(Duration::from_secs(10)),
+            request_snapshot: true,
+            local_address: None,
+            accept_compression: None,
+            #[cfg(feature = "proxy")]
+            proxy: None,
+        }
+    }
+}
+
+/// Awaits `future`, failing if it does not complete within `timeout`
+/// 
+/// # Arguments
+/// * `timeout` - The maximum time to wait, `None` to wait indefinitely
+/// * `future` - The future to await
+pub(crate) async fn with_timeout<F: Future>(timeout: Option<Duration>, future: F) -> Result<F::Output, tokio::time::error::Elapsed> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future).await,
+        None => Ok(future.await),
+    }
+}
@@ -0,0 +1,116 @@
+use {
+    crate::config::ChannelConfig,
+    futures::future::BoxFuture,
+    hyper_util::{client::legacy::connect::HttpConnector, rt::TokioIo},
+    std::{
+        collections::HashMap,
+        error::Error,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::Instant,
+    },
+    tokio::net::TcpStream,
+    tonic::transport::Uri,
+    tower_service::Service,
+};
+
+/// Details of the connection a subscription is established on
+/// 
+/// tonic performs TLS internally and does not expose the negotiated TLS version, cipher
+/// or ALPN protocol. The protocol is always HTTP/2, negotiated through ALPN `h2` when TLS
+/// is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// The address the TCP connection goes to, the proxy's when `proxied`
+    pub peer_address: SocketAddr,
+    /// The local address the TCP connection originates from
+    pub local_address: SocketAddr,
+    /// Whether TLS is applied on top of the connection, i.e. the endpoint uses `https`
+    pub tls: bool,
+    /// Whether the connection is tunneled through a proxy
+    pub proxied: bool,
+    /// When the TCP connection was established
+    pub established_at: Instant,
+}
+
+/// The latest connection established per endpoint, written by [`ObservingConnector`]
+pub(crate) type ObservedConnections = Arc<Mutex<HashMap<String, ConnectionInfo>>>;
+
+/// Builds the TCP connector used for the gRPC channel
+/// 
+/// Mirrors the connector tonic uses by default, additionally binding to the configured
+/// local address. TLS is still applied by tonic on top of it.
+/// 
+/// # Arguments
+/// * `config` - Channel settings such as the local address
+pub(crate) fn http_connector(config: &ChannelConfig) -> HttpConnector {
+    let mut connector = HttpConnector::new();
+    connector.enforce_http(false);
+    connector.set_nodelay(true);
+    connector.set_local_address(config.local_address);
+    connector
+}
+
+/// Wraps a connector, recording every connection it establishes
+#[derive(Clone)]
+pub(crate) struct ObservingConnector<C> {
+    inner: C,
+    endpoint: String,
+    proxied: bool,
+    observed: ObservedConnections,
+}
+
+impl<C> ObservingConnector<C> {
+    /// Wraps `inner`, recording its connections under `endpoint`
+    /// 
+    /// # Arguments
+    /// * `inner` - The connector establishing the TCP connection
+    /// * `endpoint` - The endpoint the channel is connected to
+    /// * `proxied` - Whether `inner` tunnels through a proxy
+    /// * `observed` - Where connections are recorded
+    pub(crate) fn new(inner: C, endpoint: &str, proxied: bool, observed: &ObservedConnections) -> Self {
+        ObservingConnector {
+            inner,
+            endpoint: endpoint.to_string(),
+            proxied,
+            observed: observed.clone(),
+        }
+    }
+}
+
+impl<C> Service<Uri> for ObservingConnector<C>
+where
+    C: Service<Uri, Response = TokioIo<TcpStream>>,
+    C::Error: Into<Box<dyn Error + Send + Sync>>,
+    C::Future: Send + 'static,
+{
+    type Response = TokioIo<TcpStream>;
+    type Error = Box<dyn Error + Send + Sync>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, target: Uri) -> Self::Future {
+        let tls = target.scheme_str() == Some("https");
+        let connecting = self.inner.call(target);
+        let endpoint = self.endpoint.clone();
+        let proxied = self.proxied;
+        let observed = self.observed.clone();
+
+        Box::pin(async move {
+            let io = connecting.await.map_err(Into::into)?;
+            let info = ConnectionInfo {
+                peer_address: io.inner().peer_addr()?,
+                local_address: io.inner().local_addr()?,
+                tls,
+                proxied,
+                established_at: Instant::now(),
+            };
+            observed.lock().unwrap().insert(endpoint, info);
+            Ok(io)
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use {
+    std::time::Duration,
+    tokio::time::Instant,
+    yellowstone_grpc_proto::geyser::SubscribeRequest,
+};
+
+/// Settings for coalescing account filter changes into a single subscription update
+#[derive(Debug, Clone)]
+pub struct FilterDebounce {
+    /// Time without further changes after which pending changes are sent
+    pub quiet_period: Duration,
+    /// Number of pending changes that are sent right away
+    pub max_batch: usize,
+    /// Name of the account filter the changes apply to, created if missing
+    pub filter: String,
+}
+
+impl Default for FilterDebounce {
+    fn default() -> Self {
+        FilterDebounce {
+            quiet_period: Duration::from_millis(200),
+            max_batch: 100,
+            filter: "accounts".to_string(),
+        }
+    }
+}
+
+/// Account filter additions and removals waiting to be sent
+#[derive(Default)]
+pub(crate) struct FilterChanges {
+    pub(crate) config: FilterDebounce,
+    changes: Vec<(String, bool)>,
+    deadline: Option<Instant>,
+}
+
+impl FilterChanges {
+    /// Returns when the pending changes have to be sent, `None` if there are none
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Queues a change, restarting the quiet period
+    /// 
+    /// # Arguments
+    /// * `account` - The base58 encoded account address
+    /// * `add` - Whether the account is added to or removed from the filter
+    pub(crate) fn push(&mut self, account: String, add: bool) {
+        self.changes.push((account, add));
+        let now = Instant::now();
+        let full = self.changes.len() >= self.config.max_batch;
+        self.deadline = Some(if full { now } else { now + self.config.quiet_period });
+    }
+
+    /// Applies the pending changes to `request`, clearing them
+    /// 
+    /// Returns the updated request, `None` if the changes left it unchanged.
+    /// 
+    /// # Arguments
+    /// * `request` - The active subscription request
+    pub(crate) fn apply(&mut self, request: &SubscribeRequest) -> Option<SubscribeRequest> {
+        self.deadline = None;
+        if self.changes.is_empty() {
+            return None;
+        }
+
+        let mut update = request.clone();
+        let filter = update.accounts.entry(self.config.filter.clone()).or_default();
+        for (account, add) in self.changes.drain(..) {
+            let present = filter.account.iter().position(|existing| *existing == account);
+            match (add, present) {
+                (true, None) => filter.account.push(account),
+                (false, Some(index)) => {
+                    filter.account.remove(index);
+                }
+                _ => {}
+            }
+        }
+
+        // An empty account list matches every account, so drop the filter instead
+        if filter.account.is_empty() && filter.owner.is_empty() && filter.filters.is_empty() {
+            update.accounts.remove(&self.config.filter);
+        }
+        (update != *request).then_some(update)
+    }
+}
@@ -0,0 +1,63 @@
+use {
+    std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    },
+    yellowstone_grpc_proto::geyser::{subscribe_update::UpdateOneof, SubscribeUpdate},
+};
+
+/// Retains the most recent updates for inspection, shared between manager and handles
+/// 
+/// A capacity of zero disables retention, leaving a single atomic load per update.
+#[derive(Default)]
+pub(crate) struct DebugBuffer {
+    capacity: AtomicUsize,
+    updates: Mutex<VecDeque<SubscribeUpdate>>,
+}
+
+impl DebugBuffer {
+    /// Changes how many updates are retained, discarding the oldest beyond it
+    /// 
+    /// # Arguments
+    /// * `capacity` - The number of updates to keep, zero to disable
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut updates = self.updates.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        while updates.len() > capacity {
+            updates.pop_front();
+        }
+        updates.shrink_to(capacity);
+    }
+
+    /// Retains an update, evicting the oldest once full
+    /// 
+    /// # Arguments
+    /// * `filters` - The names of the filters the update matched
+    /// * `update` - The update to retain
+    /// * `created_at` - When the server created the update
+    pub(crate) fn push(&self, filters: &[String], update: &UpdateOneof, created_at: Option<prost_types::Timestamp>) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+
+        let mut updates = self.updates.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if updates.len() >= capacity {
+            updates.pop_front();
+        }
+        updates.push_back(SubscribeUpdate {
+            filters: filters.to_vec(),
+            update_oneof: Some(update.clone()),
+            created_at,
+        });
+    }
+
+    /// Returns the retained updates, oldest first
+    pub(crate) fn recent(&self) -> Vec<SubscribeUpdate> {
+        let updates = self.updates.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        updates.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,33 @@
+use yellowstone_grpc_proto::geyser::SubscribeUpdateAccount;
+
+/// Decodes raw account updates into an application type
+pub trait AccountDecoder: Send + Sync + 'static {
+    type Output: Send + 'static;
+
+    /// Decodes a single account update
+    /// 
+    /// # Arguments
+    /// * `account` - The raw account update
+    fn decode(&self, account: &SubscribeUpdateAccount) -> Self::Output;
+}
+
+/// Decodes a batch of account updates, preserving their order
+/// 
+/// With the `rayon` feature enabled the batch is decoded in parallel on the rayon
+/// thread pool, otherwise sequentially.
+/// 
+/// # Arguments
+/// * `decoder` - The decoder to apply
+/// * `accounts` - The raw account updates
+pub fn decode_batch<D: AccountDecoder>(decoder: &D, accounts: &[SubscribeUpdateAccount]) -> Vec<D::Output> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        accounts.par_iter().map(|account| decoder.decode(account)).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        accounts.iter().map(|account| decoder.decode(account)).collect()
+    }
+}
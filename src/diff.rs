@@ -0,0 +1,59 @@
+use {
+    std::collections::{HashMap, VecDeque},
+    yellowstone_grpc_proto::geyser::SubscribeUpdateAccount,
+};
+
+/// Suppresses account updates whose data did not change in a way the caller cares about
+/// 
+/// The last data seen per pubkey is retained for up to `capacity` accounts, evicting the
+/// account that was added first once full. Accounts without retained data are always
+/// delivered.
+pub(crate) struct AccountDiff {
+    changed: Box<dyn Fn(&[u8], &[u8]) -> bool + Send + Sync>,
+    capacity: usize,
+    last: HashMap<Vec<u8>, Vec<u8>>,
+    order: VecDeque<Vec<u8>>,
+}
+
+impl AccountDiff {
+    /// Creates an empty diff cache
+    /// 
+    /// # Arguments
+    /// * `changed` - Decides from the previous and new data whether an update is delivered
+    /// * `capacity` - The maximum number of accounts whose data is retained
+    pub(crate) fn new(changed: Box<dyn Fn(&[u8], &[u8]) -> bool + Send + Sync>, capacity: usize) -> Self {
+        AccountDiff {
+            changed,
+            capacity,
+            last: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns whether `update` is to be delivered, retaining its data for the next one
+    /// 
+    /// # Arguments
+    /// * `update` - The account update received
+    pub(crate) fn check(&mut self, update: &SubscribeUpdateAccount) -> bool {
+        let Some(info) = &update.account else {
+            return true;
+        };
+        if let Some(previous) = self.last.get_mut(&info.pubkey) {
+            let deliver = (self.changed)(previous, &info.data);
+            previous.clone_from(&info.data);
+            return deliver;
+        }
+
+        if self.capacity == 0 {
+            return true;
+        }
+        if self.last.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.last.remove(&oldest);
+            }
+        }
+        self.last.insert(info.pubkey.clone(), info.data.clone());
+        self.order.push_back(info.pubkey.clone());
+        true
+    }
+}
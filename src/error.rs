@@ -0,0 +1,60 @@
+use {
+    futures::channel::mpsc::SendError,
+    std::time::Duration,
+    tonic::{metadata::errors::InvalidMetadataValue, Status},
+    yellowstone_grpc_client::GeyserGrpcClientError,
+};
+
+/// Errors returned by the stream manager
+#[derive(Debug, thiserror::Error)]
+pub enum GrpcStreamError {
+    /// The endpoint URL is malformed or lacks an `http` or `https` scheme
+    #[error("invalid endpoint {endpoint:?}: {reason}")]
+    InvalidEndpoint { endpoint: String, reason: String },
+    /// The `x-token` contains characters not allowed in a header, such as a trailing newline
+    #[error("invalid x-token, only visible ASCII characters are allowed: {0}")]
+    InvalidToken(#[source] InvalidMetadataValue),
+    /// The channel could not be established
+    #[error("transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    /// The server terminated the stream with an error status
+    #[error("gRPC status: {0}")]
+    Status(#[from] Status),
+    /// The geyser client failed to issue a request
+    #[error("geyser client error: {0}")]
+    Client(#[from] GeyserGrpcClientError),
+    /// A request could not be sent on the subscription stream
+    #[error("failed to send on the subscription stream: {0}")]
+    Send(#[from] SendError),
+    /// Reading or writing a recording or bookmark failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// An operation did not complete within its timeout
+    #[error("{0} timed out")]
+    Timeout(&'static str),
+    /// The request references more account filters than allowed
+    /// 
+    /// `limit` is the configured or server-reported limit, when known.
+    #[error("account filter limit exceeded (limit: {limit:?})")]
+    FilterLimitExceeded { limit: Option<usize> },
+    /// A filter was built without any effect, see the message for details
+    #[error("invalid filter: {0}")]
+    InvalidFilter(&'static str),
+    /// The channel probe found the channel unresponsive
+    #[error("channel unavailable")]
+    ChannelUnavailable,
+    /// The throughput watchdog measured fewer updates per second than required
+    #[error("throughput dropped to {rate:.2} updates/s")]
+    LowThroughput { rate: f64 },
+    /// Reconnecting was given up after the given number of consecutive attempts
+    #[error("max reconnection attempts reached ({0})")]
+    MaxReconnectAttempts(u32),
+    /// Reconnecting was given up since it would exceed the downtime budget
+    /// 
+    /// Holds the time spent disconnected so far.
+    #[error("max total downtime reached after {0:?}")]
+    MaxDowntime(Duration),
+    /// Reconnecting was given up after cycling through every endpoint of the escalation
+    #[error("all endpoints failed after {cycles} full cycles")]
+    EndpointsExhausted { cycles: u32 },
+}
@@ -0,0 +1,111 @@
+use {
+    crate::{reconnect, GrpcStreamError},
+    std::error::Error,
+    tonic::{Code, Status},
+};
+
+/// Connection lifecycle changes reported to the lifecycle handler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// The subscription was established
+    Connected,
+    /// The subscription ended, a reconnect follows unless the manager gives up or shuts down
+    Disconnected { reason: DisconnectReason },
+    /// The manager waits out the reconnect backoff after a retried failure
+    /// 
+    /// Reported for failed subscribes as well as failed streams, with `attempt` counting
+    /// the consecutive reconnect attempts including this one. Failures that end `connect`
+    /// are returned instead.
+    Reconnecting { attempt: u32, failure: StreamFailure },
+    /// The probed state of the underlying channel changed
+    ChannelStateChanged { from: ChannelState, to: ChannelState },
+    /// Periodic liveness report while connected, see `set_heartbeat_interval`
+    Heartbeat(Heartbeat),
+}
+
+/// The state reported by a [`LifecycleEvent::Heartbeat`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// The label of the manager, see `GrpcStreamManager::set_label`
+    pub label: String,
+    /// Updates received since the previous heartbeat or the subscribe
+    pub messages: u64,
+    /// Highest slot seen in any update, `None` if none carried a slot yet
+    pub slot: Option<u64>,
+    /// Number of times the subscription was re-established
+    pub reconnects: u64,
+}
+
+/// Why a subscription ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The manager was shut down or a handler asked it to stop
+    Shutdown,
+    /// The stream is re-established right away, e.g. for a requested snapshot
+    Resubscribe,
+    /// The server drained the connection with an HTTP/2 GOAWAY
+    GoAway,
+    /// The server ended the stream without an error
+    StreamClosed,
+    /// The stream failed, see [`StreamFailure`] for the error
+    StreamError(StreamFailure),
+}
+
+/// A failure captured with its gRPC status and complete source chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamFailure {
+    /// The gRPC status code, `None` for failures without a status such as timeouts
+    pub code: Option<Code>,
+    /// The status message, or the error message for failures without a status
+    pub message: String,
+    /// The messages of the underlying errors, outermost first, e.g. h2 or I/O errors
+    pub sources: Vec<String>,
+}
+
+impl StreamFailure {
+    /// Captures the status, message and source chain of an error
+    /// 
+    /// # Arguments
+    /// * `err` - The error the subscribe RPC or the stream failed with
+    pub(crate) fn capture(err: &GrpcStreamError) -> Self {
+        let status = reconnect::failure_status(err);
+        let mut source = match status {
+            Some(status) => status.source(),
+            None => err.source(),
+        };
+        let mut sources = Vec::new();
+        while let Some(inner) = source {
+            sources.push(inner.to_string());
+            source = inner.source();
+        }
+
+        StreamFailure {
+            code: status.map(Status::code),
+            message: status.map_or_else(|| err.to_string(), |status| status.message().to_string()),
+            sources,
+        }
+    }
+}
+
+/// Connectivity of the underlying channel as seen by the channel probe
+/// 
+/// tonic does not expose the connectivity state of its channels, so it is derived from
+/// periodic health checks on the channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelState {
+    /// The channel answers requests
+    Ready,
+    /// The channel failed to answer within the request timeout or is unavailable
+    TransientFailure,
+}
+
+/// In-band notices from the server that do not end the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerWarning {
+    /// An update without a known payload
+    /// 
+    /// The geyser protocol defines no dedicated error or warning update. Payload types
+    /// added by newer servers are skipped during decoding, so such updates arrive empty;
+    /// they are reported here instead of being dropped silently.
+    UnknownUpdate { filters: Vec<String> },
+}
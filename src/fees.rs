@@ -0,0 +1,70 @@
+use yellowstone_grpc_proto::geyser::SubscribeUpdateTransaction;
+
+/// Address of the ComputeBudget program, `ComputeBudget111111111111111111111111111111`
+const COMPUTE_BUDGET_PROGRAM: [u8; 32] = [
+    3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187, 197, 247, 18, 107, 44,
+    67, 155, 58, 64, 0, 0, 0,
+];
+
+/// Compute units granted per instruction when no limit is set
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+/// Maximum compute units a transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Compute budget and priority fee of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityFeeInfo {
+    /// The requested compute unit limit, or the default derived from the instruction count
+    pub compute_unit_limit: u32,
+    /// The compute unit price in micro-lamports, `0` if not set
+    pub compute_unit_price: u64,
+    /// `compute_unit_limit * compute_unit_price`, rounded up to whole lamports
+    pub priority_fee_lamports: u64,
+}
+
+/// Parses the ComputeBudget instructions of a transaction
+/// 
+/// Without a `SetComputeUnitLimit` instruction the limit is approximated as
+/// [`DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT`] per other instruction. Limits are capped at
+/// [`MAX_COMPUTE_UNIT_LIMIT`]. Returns `None` if the update carries no transaction message.
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub fn priority_fee_info(tx: &SubscribeUpdateTransaction) -> Option<PriorityFeeInfo> {
+    let message = tx.transaction.as_ref()?.transaction.as_ref()?.message.as_ref()?;
+
+    let mut limit = None;
+    let mut price = 0;
+    let mut other_instructions: u32 = 0;
+    for instruction in &message.instructions {
+        let program = message.account_keys.get(instruction.program_id_index as usize);
+        if program.map(Vec::as_slice) != Some(COMPUTE_BUDGET_PROGRAM.as_slice()) {
+            other_instructions += 1;
+            continue;
+        }
+
+        match instruction.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_LIMIT, args)) => {
+                limit = args.get(..4).map(|bytes| u32::from_le_bytes(bytes.try_into().expect("slice has 4 bytes")));
+            }
+            Some((&SET_COMPUTE_UNIT_PRICE, args)) => {
+                price = args.get(..8).map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().expect("slice has 8 bytes")));
+            }
+            _ => {}
+        }
+    }
+
+    let compute_unit_limit = limit
+        .unwrap_or(other_instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let micro_lamports = compute_unit_limit as u128 * price as u128;
+    Some(PriorityFeeInfo {
+        compute_unit_limit,
+        compute_unit_price: price,
+        priority_fee_lamports: micro_lamports.div_ceil(1_000_000) as u64,
+    })
+}
@@ -0,0 +1,216 @@
+use {
+    crate::{debug::DebugBuffer, stats::LastMessage, BackoffStrategy, ConnectionInfo},
+    std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    tokio::sync::{mpsc, watch},
+    tokio_util::sync::CancellationToken,
+    yellowstone_grpc_proto::geyser::{SubscribeRequest, SubscribeUpdate},
+};
+
+/// Commands sent from a [`StreamHandle`] to the running connect loop
+pub(crate) enum Command {
+    RequestSnapshot,
+    UpdateSubscription(SubscribeRequest),
+    AccountFilter { account: String, add: bool },
+    MaxReconnectAttempts(u32),
+    ReconnectInterval(Duration),
+    BackoffStrategy(BackoffStrategy),
+    Pause,
+    Resume,
+    Migrate { endpoint: String, x_token: Option<String> },
+}
+
+/// What happens to updates received while consumption is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PausePolicy {
+    /// Keep up to `max` updates and deliver them on resume, dropping any beyond that
+    Buffer { max: usize },
+    /// Discard all updates until resumed
+    Drop,
+}
+
+impl Default for PausePolicy {
+    fn default() -> Self {
+        PausePolicy::Buffer { max: 10_000 }
+    }
+}
+
+/// Cloneable handle controlling a running [`GrpcStreamManager`](crate::GrpcStreamManager)
+/// 
+/// `connect` holds the manager mutably for as long as it runs, so runtime control goes
+/// through a handle obtained beforehand. Commands sent after the manager is dropped are
+/// ignored.
+#[derive(Clone)]
+pub struct StreamHandle {
+    pub(crate) commands: mpsc::UnboundedSender<Command>,
+    pub(crate) shutdown: CancellationToken,
+    pub(crate) connected: watch::Receiver<bool>,
+    pub(crate) debug_buffer: Arc<DebugBuffer>,
+    pub(crate) last_message: Arc<LastMessage>,
+    pub(crate) connection_info: Arc<Mutex<Option<ConnectionInfo>>>,
+}
+
+impl StreamHandle {
+    /// Stops the stream, making `connect` return `Ok(())`
+    /// 
+    /// Takes effect immediately, including during a reconnect backoff. A manager that has
+    /// been shut down cannot be connected again.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+    }
+
+    /// Waits until the subscription is established
+    /// 
+    /// Resolves once the server has accepted the subscribe request, immediately if it
+    /// already has. Returns `false` if the manager was dropped before that happened.
+    pub async fn ready(&self) -> bool {
+        let mut connected = self.connected.clone();
+        connected.wait_for(|connected| *connected).await.is_ok()
+    }
+
+    /// Returns whether the subscription is currently established
+    pub fn is_connected(&self) -> bool {
+        *self.connected.borrow()
+    }
+
+    /// Returns when the manager last received an update, `None` if none was yet
+    /// 
+    /// Pings and pongs are not counted.
+    pub fn last_message_at(&self) -> Option<Instant> {
+        self.last_message.at()
+    }
+
+    /// Returns the time since the manager last received an update, `None` if none was yet
+    pub fn time_since_last_message(&self) -> Option<Duration> {
+        self.last_message.at().map(|at| at.elapsed())
+    }
+
+    /// Returns the connection the subscription is established on, `None` while disconnected
+    pub fn connection_info(&self) -> Option<ConnectionInfo> {
+        match self.is_connected() {
+            true => *self.connection_info.lock().unwrap(),
+            false => None,
+        }
+    }
+
+    /// Returns the updates retained by the manager's debug buffer, oldest first
+    /// 
+    /// Empty unless enabled through `set_debug_buffer`.
+    pub fn recent_updates(&self) -> Vec<SubscribeUpdate> {
+        self.debug_buffer.recent()
+    }
+
+    /// Requests a one-time snapshot of all matching accounts
+    /// 
+    /// The snapshot can only be requested when subscribing, so the stream is
+    /// re-established immediately with snapshot enabled. Later reconnects fall back to
+    /// the configured `request_snapshot` setting.
+    pub fn request_snapshot(&self) {
+        let _ = self.commands.send(Command::RequestSnapshot);
+    }
+
+    /// Adds an account to the debounced account filter
+    /// 
+    /// Changes are collected and sent as a single subscription update once no further
+    /// change arrived for the quiet period or the batch size is reached, see
+    /// `set_filter_debounce`.
+    /// 
+    /// # Arguments
+    /// * `pubkey` - The base58 encoded account address
+    pub fn add_account_filter(&self, pubkey: &str) {
+        let _ = self.commands.send(Command::AccountFilter {
+            account: pubkey.to_string(),
+            add: true,
+        });
+    }
+
+    /// Removes an account from the debounced account filter, see [`add_account_filter`](Self::add_account_filter)
+    /// 
+    /// Removing the last account removes the filter, instead of leaving an empty one that
+    /// would match every account.
+    /// 
+    /// # Arguments
+    /// * `pubkey` - The base58 encoded account address
+    pub fn remove_account_filter(&self, pubkey: &str) {
+        let _ = self.commands.send(Command::AccountFilter {
+            account: pubkey.to_string(),
+            add: false,
+        });
+    }
+
+    /// Changes how many consecutive reconnects are attempted, see
+    /// `GrpcStreamManager::set_max_reconnect_attempts`
+    /// 
+    /// Like the other reconnect settings, the change applies from the next reconnect on
+    /// and does not shorten or extend a backoff already in progress.
+    /// 
+    /// # Arguments
+    /// * `attempts` - The maximum number of consecutive reconnect attempts
+    pub fn set_max_reconnect_attempts(&self, attempts: u32) {
+        let _ = self.commands.send(Command::MaxReconnectAttempts(attempts));
+    }
+
+    /// Changes the base delay of the current backoff strategy
+    /// 
+    /// Sets the `interval` of a linear and the `initial` delay of an exponential backoff.
+    /// 
+    /// # Arguments
+    /// * `interval` - The new base delay
+    pub fn set_reconnect_interval(&self, interval: Duration) {
+        let _ = self.commands.send(Command::ReconnectInterval(interval));
+    }
+
+    /// Replaces the backoff strategy used for reconnects
+    /// 
+    /// # Arguments
+    /// * `strategy` - The new backoff strategy
+    pub fn set_backoff_strategy(&self, strategy: BackoffStrategy) {
+        let _ = self.commands.send(Command::BackoffStrategy(strategy));
+    }
+
+    /// Stops delivering updates without closing the connection
+    /// 
+    /// The stream keeps answering pings while paused, and updates are buffered or dropped
+    /// according to the manager's [`PausePolicy`].
+    pub fn pause(&self) {
+        let _ = self.commands.send(Command::Pause);
+    }
+
+    /// Resumes delivering updates, flushing any buffered ones first
+    pub fn resume(&self) {
+        let _ = self.commands.send(Command::Resume);
+    }
+
+    /// Moves the subscription to another endpoint without a gap
+    /// 
+    /// The new endpoint is subscribed with the active request in parallel. Once it
+    /// delivers an update past the highest slot seen on the current endpoint, the
+    /// manager cuts over and closes the old connection. Updates already delivered from
+    /// the old endpoint are filtered out during the overlap. If the new endpoint fails
+    /// before catching up, the current connection is kept. While disconnected, the
+    /// endpoint is switched directly.
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint to migrate to
+    /// * `x_token` - Optional authentication token for the new endpoint
+    pub fn migrate_to(&self, endpoint: &str, x_token: Option<String>) {
+        let _ = self.commands.send(Command::Migrate {
+            endpoint: endpoint.to_string(),
+            x_token,
+        });
+    }
+
+    /// Replaces the filters of the running subscription
+    /// 
+    /// The request is compared field by field against the active one and not sent if
+    /// identical, avoiding needless reprocessing on the server. The new request is also
+    /// used for all later reconnects.
+    /// 
+    /// # Arguments
+    /// * `request` - The subscription request to apply
+    pub fn update_subscription(&self, request: SubscribeRequest) {
+        let _ = self.commands.send(Command::UpdateSubscription(request));
+    }
+}
@@ -2,6 +2,1156 @@
 
 #[cfg(test)]
 pub mod tests {
+    use {
+        crate::{
+            apply_jitter,
+            bookmark::Bookmarking,
+            config::with_timeout,
+            diff::AccountDiff,
+            migration::{CatchUp, CatchUpState, HighWaterKeys, MAX_BUFFERED},
+            proto::{
+                subscribe_update::UpdateOneof, SubscribeRequest, SubscribeRequestPing, SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateAccountInfo,
+                SubscribeUpdatePing, SubscribeUpdateSlot, SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo, TransactionError, TransactionStatusMeta,
+            },
+            reconnect::{Escalation, Reconnect},
+            signature_array, signature_bytes, transaction_slot, transaction_succeeded, BackoffStrategy, Bookmark, EscalationPolicy,
+            FileBookmark, GrpcStreamError, GrpcStreamManager, MemoryBookmark, StreamFailure, StreamPosition, SubscribeRequestBuilder,
+        },
+        std::{io::ErrorKind, sync::Arc, time::Duration},
+        tokio_util::sync::CancellationToken,
+        tonic::{Code, Status},
+        yellowstone_grpc_client::GeyserGrpcClientError,
+    };
 
-    
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn channel_calls_outlive_request_timeout() {
+        use {
+            crate::{connect_channel, connector::ObservedConnections, ChannelConfig},
+            tonic::codegen::http::{Request, Response},
+            tower_service::Service,
+        };
+
+        // Answers every call only after the request timeout passed
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut connection = h2::server::handshake(socket).await.unwrap();
+            while let Some(Ok((_, mut respond))) = connection.accept().await {
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    let response = Response::builder().header("content-type", "application/grpc").body(()).unwrap();
+                    respond.send_response(response, true).unwrap();
+                });
+            }
+        });
+
+        let config = ChannelConfig {
+            request_timeout: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let mut channel = connect_channel(&format!("http://{}", address), &config, &ObservedConnections::default()).await.unwrap();
+        let request = Request::post(format!("http://{}/geyser.Geyser/Subscribe", address))
+            .header("content-type", "application/grpc")
+            .body(tonic::body::empty_body())
+            .unwrap();
+        futures::future::poll_fn(|cx| channel.poll_ready(cx)).await.unwrap();
+        let started = std::time::Instant::now();
+        let response = channel.call(request).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn request_timeout_aborts_slow_call() {
+        let slow = tokio::time::sleep(Duration::from_millis(50));
+        assert!(with_timeout(Some(Duration::from_millis(5)), slow).await.is_err());
+    }
+
+    /// Builds a transaction update fixture
+    /// 
+    /// # Arguments
+    /// * `slot` - The slot the transaction was observed in
+    /// * `err` - The serialized error for failed transactions, `None` for successful ones
+    fn transaction_fixture(slot: u64, err: Option<Vec<u8>>) -> SubscribeUpdateTransaction {
+        SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![7; 64],
+                meta: Some(TransactionStatusMeta {
+                    err: err.map(|err| TransactionError { err }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            slot,
+        }
+    }
+
+    #[test]
+    fn transaction_status() {
+        assert!(transaction_succeeded(&transaction_fixture(1, None)));
+        assert!(!transaction_succeeded(&transaction_fixture(1, Some(vec![0, 0, 0, 0]))));
+    }
+
+    #[test]
+    fn only_errored_transactions_count_as_failed() {
+        use crate::transaction::transaction_failed;
+
+        assert!(transaction_failed(&transaction_fixture(1, Some(vec![0, 0, 0, 0]))));
+        assert!(!transaction_failed(&transaction_fixture(1, None)));
+        let mut without_meta = transaction_fixture(1, None);
+        without_meta.transaction.as_mut().unwrap().meta = None;
+        assert!(!transaction_failed(&without_meta));
+    }
+
+    #[test]
+    fn transaction_slot_round_trips() {
+        assert_eq!(transaction_slot(&transaction_fixture(312_456_789, None)), 312_456_789);
+    }
+
+    #[test]
+    fn signature_access() {
+        let tx = transaction_fixture(1, None);
+        assert_eq!(signature_bytes(&tx), Some([7; 64].as_slice()));
+        assert_eq!(signature_array(&tx), Some([7; 64]));
+        assert_eq!(signature_bytes(&Default::default()), None);
+    }
+
+    /// Builds a transaction update fixture carrying the given instructions
+    /// 
+    /// # Arguments
+    /// * `account_keys` - The static account keys of the message
+    /// * `instructions` - Pairs of the program's index in `account_keys` and the instruction data
+    #[cfg(feature = "details")]
+    fn instructions_fixture(account_keys: Vec<Vec<u8>>, instructions: Vec<(u32, Vec<u8>)>) -> SubscribeUpdateTransaction {
+        use crate::proto::{CompiledInstruction, Message, Transaction};
+
+        let mut tx = transaction_fixture(1, None);
+        let info = tx.transaction.as_mut().expect("fixture has transaction info");
+        info.transaction = Some(Transaction {
+            message: Some(Message {
+                account_keys,
+                instructions: instructions
+                    .into_iter()
+                    .map(|(program_id_index, data)| CompiledInstruction {
+                        program_id_index,
+                        data,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        tx
+    }
+
+    #[cfg(feature = "details")]
+    #[test]
+    fn priority_fee_parsing() {
+        use crate::priority_fee_info;
+
+        let compute_budget = bs58::decode("ComputeBudget111111111111111111111111111111").into_vec().unwrap();
+        let keys = vec![vec![1; 32], compute_budget];
+
+        let mut limit = vec![2];
+        limit.extend(300_000u32.to_le_bytes());
+        let mut price = vec![3];
+        price.extend(1_500u64.to_le_bytes());
+        let budgeted = instructions_fixture(keys.clone(), vec![(1, limit), (1, price), (0, vec![9])]);
+        let info = priority_fee_info(&budgeted).unwrap();
+        assert_eq!((info.compute_unit_limit, info.compute_unit_price, info.priority_fee_lamports), (300_000, 1_500, 450));
+
+        let plain = instructions_fixture(keys, vec![(0, vec![9]), (0, vec![9])]);
+        let info = priority_fee_info(&plain).unwrap();
+        assert_eq!((info.compute_unit_limit, info.compute_unit_price, info.priority_fee_lamports), (400_000, 0, 0));
+
+        assert_eq!(priority_fee_info(&transaction_fixture(1, None)), None);
+    }
+
+    /// Builds an account update fixture
+    /// 
+    /// # Arguments
+    /// * `pubkey` - The raw address of the account
+    /// * `owner` - The raw address of the owning program
+    /// * `data` - The account data
+    #[cfg(feature = "details")]
+    fn account_fixture(pubkey: [u8; 32], owner: [u8; 32], data: Vec<u8>) -> SubscribeUpdateAccount {
+        SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: pubkey.to_vec(),
+                owner: owner.to_vec(),
+                lamports: 2_039_280,
+                data,
+                write_version: 42,
+                ..Default::default()
+            }),
+            slot: 1,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "details")]
+    #[test]
+    fn account_decoding() {
+        use crate::DecodedAccount;
+
+        let decoded = DecodedAccount::from_update(&account_fixture([1; 32], [0; 32], vec![3, 4])).unwrap();
+        assert_eq!(decoded.pubkey, "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi");
+        assert_eq!(decoded.owner, "11111111111111111111111111111111");
+        assert_eq!((decoded.lamports, decoded.data, decoded.write_version), (2_039_280, vec![3, 4], 42));
+        assert_eq!(DecodedAccount::from_update(&Default::default()), None);
+    }
+
+    #[cfg(feature = "solana")]
+    #[test]
+    fn transaction_error_decodes() {
+        use {crate::transaction_error, solana_sdk::transaction::TransactionError};
+
+        let err = bincode::serialize(&TransactionError::InsufficientFundsForFee).unwrap();
+        let failed = transaction_fixture(1, Some(err));
+        assert_eq!(transaction_error(&failed), Some(TransactionError::InsufficientFundsForFee));
+        assert_eq!(transaction_error(&transaction_fixture(1, None)), None);
+    }
+
+    #[test]
+    fn backoff_progression() {
+        let linear = BackoffStrategy::default();
+        let delays: Vec<u64> = (1..=7).map(|attempt| linear.delay(attempt).as_secs()).collect();
+        assert_eq!(delays, [5, 10, 15, 20, 25, 25, 25]);
+
+        let exponential = BackoffStrategy::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        let delays: Vec<u128> = (1..=6).map(|attempt| exponential.delay(attempt).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        let delay = Duration::from_secs(10);
+        for sample in [0.0, 0.25, 0.5, 0.75, 0.999] {
+            let jittered = apply_jitter(delay, 0.2, sample);
+            assert!(jittered >= Duration::from_millis(7_999) && jittered < Duration::from_secs(12));
+        }
+        assert_eq!(apply_jitter(delay, 0.0, 0.9), delay);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnect_gives_up_after_max_attempts() {
+        let shutdown = CancellationToken::new();
+        let mut reconnect = Reconnect {
+            max_attempts: 3,
+            ..Default::default()
+        };
+
+        let started = tokio::time::Instant::now();
+        for _ in 0..3 {
+            reconnect.wait(&shutdown, "test").await.unwrap();
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(5 + 10 + 15) && elapsed < Duration::from_secs(31));
+        assert!(matches!(reconnect.wait(&shutdown, "test").await, Err(GrpcStreamError::MaxReconnectAttempts(3))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnect_gives_up_after_max_downtime() {
+        let shutdown = CancellationToken::new();
+        let mut reconnect = Reconnect {
+            max_total_downtime: Some(Duration::from_secs(20)),
+            ..Default::default()
+        };
+
+        reconnect.wait(&shutdown, "test").await.unwrap();
+        reconnect.wait(&shutdown, "test").await.unwrap();
+        assert!(matches!(reconnect.wait(&shutdown, "test").await, Err(GrpcStreamError::MaxDowntime(_))));
+
+        reconnect.reset();
+        reconnect.wait(&shutdown, "test").await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn adaptive_backoff_decays_with_uptime() {
+        let shutdown = CancellationToken::new();
+        let mut reconnect = Reconnect {
+            strategy: BackoffStrategy::Adaptive {
+                initial: Duration::from_secs(1),
+                step: Duration::from_secs(1),
+                max: Duration::from_secs(5),
+                decay: 0.5,
+                decay_period: Duration::from_secs(10),
+            },
+            ..Default::default()
+        };
+
+        let mut delays = Vec::new();
+        for _ in 0..3 {
+            reconnect.wait(&shutdown, "test").await.unwrap();
+            delays.push(reconnect.last_backoff.unwrap().as_secs());
+        }
+        // Up for one decay period, halving the next backoff of 4s
+        reconnect.reset();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        for _ in 0..2 {
+            reconnect.wait(&shutdown, "test").await.unwrap();
+            delays.push(reconnect.last_backoff.unwrap().as_secs());
+        }
+        assert_eq!(delays, [1, 2, 3, 2, 3]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnect_backoff_ends_on_shutdown() {
+        let shutdown = CancellationToken::new();
+        let mut reconnect = Reconnect::default();
+        shutdown.cancel();
+
+        let started = tokio::time::Instant::now();
+        reconnect.wait(&shutdown, "test").await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn subscribe_and_stream_failures_share_retry_policy() {
+        let shutdown = CancellationToken::new();
+        let mut reconnect = Reconnect::default();
+        let subscribe_failure = |status| GrpcStreamError::Client(GeyserGrpcClientError::TonicStatus(status));
+
+        let stream_failure = GrpcStreamError::Status(Status::unauthenticated("bad token"));
+        assert!(matches!(reconnect.recover(stream_failure, &shutdown, "test").await, Err(GrpcStreamError::Status(_))));
+        let rejected = subscribe_failure(Status::unauthenticated("bad token"));
+        assert!(matches!(reconnect.recover(rejected, &shutdown, "test").await, Err(GrpcStreamError::Client(_))));
+
+        let started = tokio::time::Instant::now();
+        reconnect.recover(GrpcStreamError::Status(Status::unavailable("restarting")), &shutdown, "test").await.unwrap();
+        assert_eq!(started.elapsed().as_secs(), 5);
+        reconnect.recover(subscribe_failure(Status::resource_exhausted("slow down")), &shutdown, "test").await.unwrap();
+        assert_eq!(started.elapsed().as_secs(), 5 + 40);
+        reconnect.recover(GrpcStreamError::Timeout("subscribe request"), &shutdown, "test").await.unwrap();
+        assert_eq!(started.elapsed().as_secs(), 5 + 40 + 15);
+    }
+
+    /// Returns the status a stream fails with after the server sent a GOAWAY
+    /// 
+    /// # Arguments
+    /// * `reason` - The error code of the GOAWAY
+    async fn go_away_status(reason: h2::Reason) -> Status {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let mut connection = h2::server::handshake(server_io).await.unwrap();
+            connection.abrupt_shutdown(reason);
+            while connection.accept().await.is_some() {}
+        });
+        let (mut client, connection) = h2::client::handshake(client_io).await.unwrap();
+        tokio::spawn(connection);
+
+        let request = tonic::codegen::http::Request::post("http://localhost/geyser.Geyser/Subscribe").body(()).unwrap();
+        let err = match client.send_request(request, false) {
+            Ok((response, _)) => response.await.unwrap_err(),
+            Err(err) => err,
+        };
+        let mut status = Status::unavailable("connection closed");
+        status.set_source(Arc::new(err));
+        status
+    }
+
+    #[tokio::test]
+    async fn failed_subscribe_reconnects_like_a_failed_stream() {
+        use crate::{DisconnectReason, LifecycleEvent};
+
+        let mut server = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&server.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        manager.set_backoff_strategy(BackoffStrategy::Linear { interval: Duration::from_millis(10), max_multiplier: 1 });
+        let (events, mut reported) = tokio::sync::mpsc::unbounded_channel();
+        manager.set_lifecycle_handler(Box::new(move |event, _, _| {
+            let _ = events.send(event);
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+
+        let unavailable = |failure: &StreamFailure| failure.code == Some(Code::Unavailable);
+        let mut call = server.call().await;
+        call.send(slot_update(1));
+        call.fail(Code::Unavailable);
+        assert_eq!(recv(&mut reported).await, LifecycleEvent::Connected);
+        assert!(matches!(recv(&mut reported).await, LifecycleEvent::Disconnected { reason: DisconnectReason::StreamError(failure) } if unavailable(&failure)));
+        assert!(matches!(recv(&mut reported).await, LifecycleEvent::Reconnecting { attempt: 1, failure } if unavailable(&failure)));
+
+        // The subscribe RPC is rejected before the stream is established
+        server.call().await.fail(Code::Unavailable);
+        assert!(matches!(recv(&mut reported).await, LifecycleEvent::Reconnecting { attempt: 2, failure } if unavailable(&failure)));
+
+        let mut resubscribed = server.call().await;
+        resubscribed.send(slot_update(2));
+        assert_eq!(recv(&mut reported).await, LifecycleEvent::Connected);
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn label_reaches_stats_and_lifecycle_events() {
+        use crate::LifecycleEvent;
+
+        let mut server = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&server.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        assert_eq!(manager.stats().label, "127.0.0.1");
+        manager.set_label(Some("liquidations".to_string()));
+        assert_eq!(manager.stats().label, "liquidations");
+        manager.set_heartbeat_interval(Some(Duration::from_millis(10)));
+        let (events, mut reported) = tokio::sync::mpsc::unbounded_channel();
+        manager.set_lifecycle_handler(Box::new(move |event, _, label| {
+            let _ = events.send((event, label.to_string()));
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+
+        let mut call = server.call().await;
+        call.send(slot_update(1));
+        assert_eq!(recv(&mut reported).await, (LifecycleEvent::Connected, "liquidations".to_string()));
+        match recv(&mut reported).await {
+            (LifecycleEvent::Heartbeat(heartbeat), label) => assert!(heartbeat.label == "liquidations" && label == "liquidations"),
+            other => panic!("unexpected event {:?}", other),
+        }
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn go_away_is_found_in_the_status_sources() {
+        use crate::reconnect::is_graceful_go_away;
+
+        assert!(is_graceful_go_away(&go_away_status(h2::Reason::NO_ERROR).await));
+        // Only a drain without an error code skips the backoff
+        assert!(!is_graceful_go_away(&go_away_status(h2::Reason::ENHANCE_YOUR_CALM).await));
+        assert!(!is_graceful_go_away(&Status::unavailable("connection closed")));
+    }
+
+    #[test]
+    fn stream_failure_keeps_status_and_sources() {
+        let mut status = Status::unavailable("connection reset");
+        status.set_source(Arc::new(std::io::Error::new(ErrorKind::ConnectionReset, "h2 protocol error")));
+        let failure = StreamFailure::capture(&GrpcStreamError::Client(GeyserGrpcClientError::TonicStatus(status)));
+        assert_eq!(failure.code, Some(Code::Unavailable));
+        assert_eq!(failure.message, "connection reset");
+        assert_eq!(failure.sources, vec!["h2 protocol error".to_string()]);
+
+        let timeout = StreamFailure::capture(&GrpcStreamError::Timeout("subscribe request"));
+        assert_eq!(timeout.code, None);
+        assert!(timeout.sources.is_empty());
+    }
+
+    #[test]
+    fn escalation_cycles_through_endpoints() {
+        let mut escalation = Escalation {
+            policy: EscalationPolicy {
+                attempts_per_endpoint: 2,
+                full_cycles_before_fatal: 2,
+            },
+            endpoints: ["primary", "fallback"].map(|endpoint| (endpoint.to_string(), None)).to_vec(),
+            index: 0,
+            cycles: 0,
+        };
+
+        assert!(!escalation.due(1) && escalation.due(2));
+        escalation.advance().unwrap();
+        assert_eq!(escalation.current().0, "fallback");
+        escalation.advance().unwrap();
+        assert_eq!(escalation.current().0, "primary");
+        escalation.advance().unwrap();
+        assert!(matches!(escalation.advance(), Err(GrpcStreamError::EndpointsExhausted { cycles: 2 })));
+    }
+
+    #[test]
+    fn bookmark_commits_highest_handled_position() {
+        let memory = MemoryBookmark::new();
+        let mut bookmarking = Bookmarking::new(Box::new(memory.clone()), 2);
+        let position = |slot, within_slot| StreamPosition { slot, within_slot };
+        assert_eq!(bookmarking.start(None).unwrap(), None);
+
+        bookmarking.advance(position(10, Some(4)), false).unwrap();
+        assert_eq!(memory.position(), None);
+        bookmarking.advance(position(10, Some(2)), false).unwrap();
+        assert_eq!(memory.position(), Some(position(10, Some(4))));
+        // Held back while an earlier update waits for a retry
+        bookmarking.advance(position(11, None), true).unwrap();
+        bookmarking.advance(position(12, None), true).unwrap();
+        assert_eq!(memory.position(), Some(position(10, Some(4))));
+        bookmarking.discard();
+        bookmarking.commit().unwrap();
+        assert_eq!(bookmarking.resume_slot(), Some(10));
+
+        // The stored position only applies without an explicit start slot
+        assert_eq!(bookmarking.start(None).unwrap(), Some(10));
+        assert_eq!(bookmarking.start(Some(5)).unwrap(), Some(5));
+        assert_eq!(bookmarking.resume_slot(), None);
+        bookmarking.advance(position(6, None), false).unwrap();
+        bookmarking.advance(position(7, None), false).unwrap();
+        assert_eq!(memory.position(), Some(position(7, None)));
+
+        let path = std::env::temp_dir().join(format!("bookmark-{}", std::process::id()));
+        let mut file = FileBookmark::new(&path);
+        assert_eq!(file.load().unwrap(), None);
+        file.commit(position(12, Some(7))).unwrap();
+        assert_eq!(file.load().unwrap(), Some(position(12, Some(7))));
+        file.commit(position(13, None)).unwrap();
+        assert_eq!(file.load().unwrap(), Some(position(13, None)));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn file_bookmark_stages_next_to_its_path() {
+        let dir = std::env::temp_dir().join(format!("bookmark-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let position = StreamPosition { slot: 5, within_slot: None };
+
+        // A sibling sharing the file stem is not the staging file
+        std::fs::write(dir.join("pos.tmp"), "sibling").unwrap();
+        let mut json = FileBookmark::new(dir.join("pos.json"));
+        json.commit(position).unwrap();
+        assert_eq!(json.load().unwrap(), Some(position));
+        assert_eq!(std::fs::read_to_string(dir.join("pos.tmp")).unwrap(), "sibling");
+
+        let mut tmp = FileBookmark::new(dir.join("pos.tmp"));
+        tmp.commit(position).unwrap();
+        assert_eq!(tmp.load().unwrap(), Some(position));
+        assert!(!dir.join("pos.tmp.tmp").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recording_replays_what_was_recorded() {
+        use {
+            crate::recording::{Recorder, Replay, MAX_UPDATE_LEN},
+            prost_types::Timestamp,
+            tokio::{fs::OpenOptions, io::AsyncWriteExt},
+        };
+
+        let path = std::env::temp_dir().join(format!("recording-{}", std::process::id()));
+        let mut recorded = [message(slot_update(1)), message(slot_update(2))];
+        recorded[0].filters = vec!["slots".to_string()];
+        recorded[1].created_at = Some(Timestamp { seconds: 1, nanos: 0 });
+        // Records the updates followed by the raw `tail`
+        let record = |tail: Vec<u8>| {
+            let (path, recorded) = (&path, &recorded);
+            async move {
+                let mut recorder = Recorder::create(path).await.unwrap();
+                for update in recorded {
+                    recorder.write(update).await.unwrap();
+                }
+                recorder.finish().await.unwrap();
+                OpenOptions::new().append(true).open(path).await.unwrap().write_all(&tail).await.unwrap();
+            }
+        };
+
+        record(Vec::new()).await;
+        let mut replay = Replay::open(&path).await.unwrap();
+        for update in &recorded {
+            assert_eq!(replay.next(false).await.unwrap().as_ref(), Some(update));
+        }
+        assert_eq!(replay.next(false).await.unwrap(), None);
+
+        // Cut off inside a length prefix, and a length no update has
+        for tail in [vec![1, 0], (MAX_UPDATE_LEN as u32 + 1).to_le_bytes().to_vec()] {
+            record(tail).await;
+            let mut replay = Replay::open(&path).await.unwrap();
+            for _ in &recorded {
+                replay.next(false).await.unwrap();
+            }
+            assert_eq!(replay.next(false).await.unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn account_diff_suppresses_unchanged_fields() {
+        let update = |pubkey: u8, data: [u8; 2]| SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: vec![pubkey; 32],
+                data: data.to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        // Only the first byte matters to the handler
+        let mut diff = AccountDiff::new(Box::new(|old, new| old[0] != new[0]), 1);
+
+        assert!(diff.check(&update(1, [0, 0])));
+        assert!(!diff.check(&update(1, [0, 9])));
+        assert!(diff.check(&update(1, [5, 9])));
+        assert!(diff.check(&update(2, [5, 9])));
+        // Evicted by the second account
+        assert!(diff.check(&update(1, [5, 9])));
+    }
+
+    fn slot_update(slot: u64) -> UpdateOneof {
+        UpdateOneof::Slot(SubscribeUpdateSlot { slot, ..Default::default() })
+    }
+
+    fn message(update: UpdateOneof) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(update),
+            ..Default::default()
+        }
+    }
+
+    /// Builds an account update fixture
+    /// 
+    /// # Arguments
+    /// * `slot` - The slot the account was updated in
+    /// * `write_version` - The write version identifying the update
+    fn account_update(slot: u64, write_version: u64) -> UpdateOneof {
+        UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: vec![3; 32],
+                write_version,
+                ..Default::default()
+            }),
+            slot,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the high-water keys after delivering `updates`
+    fn high_water(updates: &[UpdateOneof]) -> HighWaterKeys {
+        let mut high_water = HighWaterKeys::default();
+        for update in updates {
+            high_water.observe(update);
+        }
+        high_water
+    }
+
+    /// A geyser server handing out its subscribe calls to the test
+    struct MockGeyser {
+        endpoint: String,
+        calls: tokio::sync::mpsc::UnboundedReceiver<MockCall>,
+    }
+
+    /// A subscribe call accepted by a [`MockGeyser`]
+    struct MockCall {
+        requests: h2::RecvStream,
+        received: Vec<u8>,
+        respond: h2::server::SendResponse<tonic::codegen::Bytes>,
+        updates: Option<h2::SendStream<tonic::codegen::Bytes>>,
+    }
+
+    impl MockGeyser {
+        async fn start() -> MockGeyser {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+            let (calls_tx, calls) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    let calls_tx = calls_tx.clone();
+                    tokio::spawn(async move {
+                        let Ok(mut connection) = h2::server::handshake(socket).await else {
+                            return;
+                        };
+                        while let Some(Ok((request, respond))) = connection.accept().await {
+                            let call = MockCall {
+                                requests: request.into_body(),
+                                received: Vec::new(),
+                                respond,
+                                updates: None,
+                            };
+                            let _ = calls_tx.send(call);
+                        }
+                    });
+                }
+            });
+            MockGeyser { endpoint, calls }
+        }
+
+        /// Waits for the next subscribe call
+        async fn call(&mut self) -> MockCall {
+            tokio::time::timeout(Duration::from_secs(5), self.calls.recv()).await.expect("no subscribe call").unwrap()
+        }
+    }
+
+    impl MockCall {
+        /// Reads the next request sent on the call
+        async fn request(&mut self) -> SubscribeRequest {
+            loop {
+                if self.received.len() >= 5 {
+                    let len = u32::from_be_bytes(self.received[1..5].try_into().unwrap()) as usize;
+                    if self.received.len() >= 5 + len {
+                        let frame: Vec<u8> = self.received.drain(..5 + len).collect();
+                        return prost::Message::decode(&frame[5..]).unwrap();
+                    }
+                }
+                let chunk = tokio::time::timeout(Duration::from_secs(5), self.requests.data()).await.expect("no request").unwrap().unwrap();
+                let _ = self.requests.flow_control().release_capacity(chunk.len());
+                self.received.extend_from_slice(&chunk);
+            }
+        }
+
+        /// Streams an update, answering the call first if needed
+        fn send(&mut self, update: UpdateOneof) {
+            let body = prost::Message::encode_to_vec(&message(update));
+            let mut frame = vec![0];
+            frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&body);
+            let updates = self.updates.get_or_insert_with(|| {
+                let response = tonic::codegen::http::Response::builder().header("content-type", "application/grpc").body(()).unwrap();
+                self.respond.send_response(response, false).unwrap()
+            });
+            updates.send_data(frame.into(), false).unwrap();
+        }
+
+        /// Sends a ping and waits for its reply, by which the client read all earlier updates
+        async fn ping(&mut self) {
+            self.send(UpdateOneof::Ping(SubscribeUpdatePing::default()));
+            while self.request().await.ping.is_none() {}
+        }
+
+        /// Ends the call with a status, as a trailers-only response if nothing was streamed yet
+        fn fail(mut self, code: Code) {
+            match self.updates.take() {
+                Some(mut updates) => {
+                    let mut trailers = tonic::codegen::http::HeaderMap::new();
+                    trailers.insert("grpc-status", (code as i32).into());
+                    updates.send_trailers(trailers).unwrap();
+                }
+                None => {
+                    let response = tonic::codegen::http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .header("grpc-status", code as i32)
+                        .body(())
+                        .unwrap();
+                    self.respond.send_response(response, true).unwrap();
+                }
+            }
+        }
+
+        /// Aborts the call, closing the stream the client sends its requests on
+        fn reset(mut self) {
+            match self.updates.take() {
+                Some(mut updates) => updates.send_reset(h2::Reason::CANCEL),
+                None => self.respond.send_reset(h2::Reason::CANCEL),
+            }
+        }
+    }
+
+    /// Waits for the next value reported by a handler
+    async fn recv<T>(events: &mut tokio::sync::mpsc::UnboundedReceiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), events.recv()).await.expect("nothing reported").unwrap()
+    }
+
+    #[test]
+    fn migration_cuts_over_past_the_delivered_slot() {
+        let mut catch_up = CatchUp::new(&high_water(&[slot_update(100), account_update(100, 1)]));
+        // Replayed from before the position the current stream reached
+        assert_eq!(catch_up.buffer(message(slot_update(90))), CatchUpState::Behind);
+        catch_up.observe(&slot_update(101));
+        assert_eq!(catch_up.buffer(message(slot_update(101))), CatchUpState::Behind);
+        assert_eq!(catch_up.buffer(message(slot_update(102))), CatchUpState::CaughtUp);
+
+        let (buffered, dedup) = catch_up.cutover();
+        assert_eq!(buffered.len(), 2);
+        assert!(dedup.contains(&slot_update(101)) && !dedup.contains(&slot_update(102)));
+        // Delivered for the high-water slot before the migration started
+        assert!(dedup.contains(&account_update(100, 1)) && !dedup.contains(&account_update(100, 2)));
+        assert!(!dedup.expired_by(&slot_update(101 + 32)));
+        assert!(dedup.expired_by(&slot_update(101 + 33)));
+    }
+
+    #[test]
+    fn migration_bounds_remembered_and_buffered_updates() {
+        let mut catch_up = CatchUp::new(&HighWaterKeys::default());
+        catch_up.observe(&slot_update(1));
+        catch_up.observe(&slot_update(40));
+        let (_, dedup) = catch_up.cutover();
+        assert!(!dedup.contains(&slot_update(1)));
+        assert!(dedup.contains(&slot_update(40)));
+
+        let mut catch_up = CatchUp::new(&high_water(&[slot_update(1_000)]));
+        for _ in 0..MAX_BUFFERED {
+            assert_eq!(catch_up.buffer(message(slot_update(1))), CatchUpState::Behind);
+        }
+        assert_eq!(catch_up.buffer(message(slot_update(1_001))), CatchUpState::Overflowed);
+    }
+
+    #[tokio::test]
+    async fn migration_delivers_the_high_water_slot_once() {
+        let mut current = MockGeyser::start().await;
+        let mut next = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&current.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        let (events, mut delivered) = tokio::sync::mpsc::unbounded_channel();
+        manager.set_account_handler(Box::new(move |account, _| {
+            let _ = events.send((account.slot, account.account.unwrap().write_version));
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+
+        let mut old = current.call().await;
+        old.send(account_update(100, 1));
+        assert_eq!(recv(&mut delivered).await, (100, 1));
+
+        handle.migrate_to(&next.endpoint, None);
+        let mut new = next.call().await;
+        assert_eq!(new.request().await, old.request().await);
+        // The new endpoint replays the slot the migration started at
+        new.send(account_update(100, 1));
+        new.send(slot_update(101));
+        new.send(account_update(101, 2));
+        assert_eq!(recv(&mut delivered).await, (101, 2));
+
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+        assert!(delivered.try_recv().is_err());
+        drop(old);
+    }
+
+    #[tokio::test]
+    async fn closed_request_stream_reconnects() {
+        use crate::{DisconnectReason, LifecycleEvent};
+
+        let mut server = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&server.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        manager.set_backoff_strategy(BackoffStrategy::Linear { interval: Duration::from_millis(10), max_multiplier: 1 });
+        let (events, mut reported) = tokio::sync::mpsc::unbounded_channel();
+        manager.set_lifecycle_handler(Box::new(move |event, _, _| {
+            let _ = events.send(event);
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+
+        // Whether the ping reply or the read sees the closed call first, the stream reconnects
+        let mut call = server.call().await;
+        call.send(UpdateOneof::Ping(SubscribeUpdatePing::default()));
+        call.reset();
+        assert_eq!(recv(&mut reported).await, LifecycleEvent::Connected);
+        assert!(matches!(recv(&mut reported).await, LifecycleEvent::Disconnected { reason: DisconnectReason::StreamError(_) }));
+        assert!(matches!(recv(&mut reported).await, LifecycleEvent::Reconnecting { attempt: 1, .. }));
+
+        let mut resubscribed = server.call().await;
+        resubscribed.send(slot_update(1));
+        assert_eq!(recv(&mut reported).await, LifecycleEvent::Connected);
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn paused_updates_are_delivered_in_order_on_resume() {
+        let mut server = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&server.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        let (events, mut delivered) = tokio::sync::mpsc::unbounded_channel();
+        manager.set_slot_handler(Box::new(move |slot, _| {
+            let _ = events.send(slot.slot);
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+
+        let mut call = server.call().await;
+        call.send(slot_update(1));
+        assert_eq!(recv(&mut delivered).await, 1);
+
+        handle.pause();
+        call.ping().await;
+        call.send(slot_update(2));
+        call.send(slot_update(3));
+        call.ping().await;
+        assert!(delivered.try_recv().is_err());
+
+        handle.resume();
+        call.send(slot_update(4));
+        for slot in [2, 3, 4] {
+            assert_eq!(recv(&mut delivered).await, slot);
+        }
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn handler_retries_hold_the_bookmark_until_exhausted() {
+        use {
+            crate::{HandlerOutcome, HandlerRetry, RetryExhausted},
+            std::sync::atomic::{AtomicBool, Ordering},
+        };
+
+        let tx = |index| {
+            let mut tx = transaction_fixture(10, None);
+            tx.transaction.as_mut().unwrap().index = index;
+            UpdateOneof::Transaction(tx)
+        };
+        let position = |index| Some(StreamPosition { slot: 10, within_slot: Some(index) });
+        let mut server = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&server.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        let memory = MemoryBookmark::new();
+        manager.set_bookmark(Box::new(memory.clone()), 1);
+        manager.set_handler_retry(HandlerRetry {
+            max_retries: 2,
+            delay: Duration::from_millis(10),
+            on_exhausted: RetryExhausted::Stop,
+        });
+        let (events, mut handled) = tokio::sync::mpsc::unbounded_channel();
+        let committed = memory.clone();
+        let retried = AtomicBool::new(false);
+        manager.set_transaction_outcome_handler(Box::new(move |update, _| {
+            let index = update.transaction.as_ref().unwrap().index;
+            let _ = events.send((index, committed.position()));
+            match index {
+                1 if !retried.swap(true, Ordering::Relaxed) => HandlerOutcome::Retry,
+                3 => HandlerOutcome::Retry,
+                _ => HandlerOutcome::Continue,
+            }
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move {
+            let result = manager.connect(SubscribeRequestBuilder::new().build()).await;
+            (manager, result)
+        });
+
+        // Delivered together on resume, so the second is handled while the first awaits its retry
+        let mut call = server.call().await;
+        handle.pause();
+        call.ping().await;
+        call.send(tx(1));
+        call.send(tx(2));
+        call.ping().await;
+        handle.resume();
+        assert_eq!(recv(&mut handled).await, (1, None));
+        assert_eq!(recv(&mut handled).await, (2, None));
+        assert_eq!(recv(&mut handled).await, (1, None));
+
+        // Invoked once and then for every retry before stopping
+        call.send(tx(3));
+        for _ in 0..3 {
+            assert_eq!(recv(&mut handled).await, (3, position(2)));
+        }
+        let (mut manager, result) = task.await.unwrap();
+        result.unwrap();
+        assert_eq!(memory.position(), position(2));
+        assert!(handled.try_recv().is_err());
+
+        manager.set_handler_retry(HandlerRetry {
+            max_retries: 2,
+            delay: Duration::from_millis(10),
+            on_exhausted: RetryExhausted::Drop,
+        });
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+        let mut call = server.call().await;
+        call.send(tx(3));
+        call.send(tx(4));
+        for _ in 0..3 {
+            assert_eq!(recv(&mut handled).await, (3, position(2)));
+        }
+        assert_eq!(recv(&mut handled).await, (4, position(2)));
+        call.ping().await;
+        assert_eq!(memory.position(), position(4));
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn snapshot_completes_on_the_first_live_update() {
+        let account = |key: u8, is_startup| {
+            let mut account = SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo { pubkey: vec![key; 32], ..Default::default() }),
+                slot: 10,
+                ..Default::default()
+            };
+            account.is_startup = is_startup;
+            UpdateOneof::Account(account)
+        };
+        let mut server = MockGeyser::start().await;
+        let mut manager = GrpcStreamManager::new(&server.endpoint, None, Box::new(|_, _| {})).await.unwrap();
+        manager.set_backoff_strategy(BackoffStrategy::Linear { interval: Duration::from_millis(10), max_multiplier: 1 });
+        manager.set_skip_snapshot(true);
+        let (events, mut seen) = tokio::sync::mpsc::unbounded_channel();
+        let (accounts, completed, reconciled) = (events.clone(), events.clone(), events);
+        manager.set_account_handler(Box::new(move |account, _| {
+            let _ = accounts.send(format!("account {}", account.account.unwrap().pubkey[0]));
+        }));
+        manager.set_snapshot_complete_handler(Box::new(move |_| {
+            let _ = completed.send("complete".to_string());
+        }));
+        manager.set_snapshot_reconcile_handler(Box::new(move |pubkeys, _| {
+            let _ = reconciled.send(format!("reconcile {}", pubkeys.join(",")));
+        }));
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(SubscribeRequestBuilder::new().build()).await });
+
+        let base58 = |key: u8| bs58::encode([key; 32]).into_string();
+        let mut call = server.call().await;
+        call.send(account(1, true));
+        call.send(account(2, true));
+        call.send(account(3, false));
+        call.send(account(4, false));
+        assert_eq!(recv(&mut seen).await, "complete");
+        assert_eq!(recv(&mut seen).await, format!("reconcile {},{}", base58(1), base58(2)));
+        assert_eq!(recv(&mut seen).await, "account 3");
+        assert_eq!(recv(&mut seen).await, "account 4");
+
+        // Every connection replays the snapshot
+        call.fail(Code::Unavailable);
+        let mut call = server.call().await;
+        call.send(account(1, true));
+        call.send(slot_update(11));
+        assert_eq!(recv(&mut seen).await, "complete");
+        assert_eq!(recv(&mut seen).await, format!("reconcile {}", base58(1)));
+        call.ping().await;
+        assert!(seen.try_recv().is_err());
+        handle.shutdown();
+        task.await.unwrap().unwrap();
+    }
+
+    #[cfg(feature = "proxy")]
+    #[test]
+    fn proxy_selection_follows_env_precedence() {
+        use {
+            crate::{
+                proxy::{bypasses_proxy, select_proxy},
+                ChannelConfig,
+            },
+            std::collections::HashMap,
+        };
+
+        let env = HashMap::from([
+            ("https_proxy", "proxy.corp:3128"),
+            ("HTTPS_PROXY", "http://upper.corp:3128"),
+            ("ALL_PROXY", "socks5h://fallback.corp"),
+            ("no_proxy", "internal.example, .svc.local"),
+        ]);
+        let select = |endpoint: &str, config: &ChannelConfig| {
+            let proxy = select_proxy(&endpoint.parse().unwrap(), config, |name| env.get(name).map(|value| value.to_string()))?;
+            Some((proxy.scheme_str()?.to_string(), proxy.host()?.to_string(), proxy.port_u16()))
+        };
+        let proxy = |scheme: &str, host: &str, port| Some((scheme.to_string(), host.to_string(), port));
+
+        let config = ChannelConfig::default();
+        // Lowercase wins, a missing scheme defaults to HTTP
+        assert_eq!(select("https://grpc.example.com", &config), proxy("http", "proxy.corp", Some(3128)));
+        assert_eq!(select("http://grpc.example.com", &config), proxy("socks5h", "fallback.corp", None));
+        assert_eq!(select("https://internal.example", &config), None);
+        assert_eq!(select("https://node.svc.local", &config), None);
+        assert_eq!(select("https://notsvc.local", &config), proxy("http", "proxy.corp", Some(3128)));
+
+        let explicit = ChannelConfig {
+            proxy: Some("explicit.corp:8080".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(select("https://internal.example", &explicit), proxy("http", "explicit.corp", Some(8080)));
+
+        assert!(bypasses_proxy("grpc.example.com", Some("*".to_string())));
+        assert!(!bypasses_proxy("grpc.example.com", Some(" , ".to_string())));
+        assert!(!bypasses_proxy("grpc.example.com", None));
+    }
+
+    #[test]
+    fn construction_errors_name_the_bad_input() {
+        use crate::{parse_endpoint, parse_token};
+
+        assert!(matches!(parse_token(Some("token\n".to_string())), Err(GrpcStreamError::InvalidToken(_))));
+        assert!(matches!(parse_endpoint("grpc.example.com:10000"), Err(GrpcStreamError::InvalidEndpoint { .. })));
+        assert!(matches!(parse_endpoint("https://grpc.example.com\n"), Err(GrpcStreamError::InvalidEndpoint { .. })));
+        assert!(parse_endpoint("https://grpc.example.com:443").is_ok());
+    }
+
+    #[test]
+    fn ping_survives_resubscribe_filter_changes() {
+        use crate::debounce::FilterChanges;
+
+        // Resubscribes send the active request with the pending filter changes applied
+        let request = SubscribeRequestBuilder::new().ping(7).build();
+        let mut changes = FilterChanges::default();
+        changes.push("Acc1".to_string(), true);
+        let resubscribed = changes.apply(&request).unwrap();
+        assert_eq!(resubscribed.ping, Some(SubscribeRequestPing { id: 7 }));
+        assert_eq!(resubscribed.accounts["accounts"].account, ["Acc1"]);
+    }
+
+    #[test]
+    fn sequence_tracker_reports_regressions() {
+        use crate::{ordering::SequenceTracker, OrderingViolation, UpdateKind};
+
+        let mut tracker = SequenceTracker::default();
+        let slot = |slot, status| UpdateOneof::Slot(SubscribeUpdateSlot { slot, status, ..Default::default() });
+        assert_eq!(tracker.check(&slot(10, 0)), None);
+        // Each status progresses on its own
+        assert_eq!(tracker.check(&slot(9, 1)), None);
+        assert_eq!(tracker.check(&slot(11, 0)), None);
+        let violation = OrderingViolation { kind: UpdateKind::Slot, slot: 10, previous: 11, current: 10 };
+        assert_eq!(tracker.check(&slot(10, 0)), Some(violation));
+
+        let account = |write_version, is_startup| {
+            let mut account = SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo { write_version, ..Default::default() }),
+                slot: 5,
+                ..Default::default()
+            };
+            account.is_startup = is_startup;
+            UpdateOneof::Account(account)
+        };
+        assert_eq!(tracker.check(&account(5, false)), None);
+        let violation = OrderingViolation { kind: UpdateKind::Account, slot: 5, previous: 5, current: 5 };
+        assert_eq!(tracker.check(&account(5, false)), Some(violation));
+        // Snapshot accounts arrive in arbitrary order
+        assert_eq!(tracker.check(&account(1, true)), None);
+        assert_eq!(tracker.check(&account(6, false)), None);
+
+        let tx = |slot, index| {
+            let mut tx = transaction_fixture(slot, None);
+            tx.transaction.as_mut().unwrap().index = index;
+            UpdateOneof::Transaction(tx)
+        };
+        assert_eq!(tracker.check(&tx(20, 3)), None);
+        assert_eq!(tracker.check(&tx(20, 4)), None);
+        // Indexes restart with every slot
+        assert_eq!(tracker.check(&tx(21, 0)), None);
+        let violation = OrderingViolation { kind: UpdateKind::Transaction, slot: 21, previous: 0, current: 0 };
+        assert_eq!(tracker.check(&tx(21, 0)), Some(violation));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn batcher_flushes_on_size_or_deadline() {
+        use {
+            crate::{batch::Batcher, BatchConfig},
+            std::sync::Mutex,
+        };
+
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let sizes = flushed.clone();
+        let config = BatchConfig {
+            batch_size: 2,
+            batch_timeout: Duration::from_secs(1),
+        };
+        let mut batcher = Batcher::new(config, Box::new(move |batch: Vec<UpdateOneof>, _: &str| sizes.lock().unwrap().push(batch.len())));
+
+        batcher.push(slot_update(1), "test");
+        batcher.push(slot_update(2), "test");
+        assert_eq!(*flushed.lock().unwrap(), [2]);
+        assert_eq!(batcher.deadline(), None);
+
+        batcher.push(slot_update(3), "test");
+        let deadline = batcher.deadline().unwrap();
+        batcher.flush_expired("test");
+        assert_eq!(*flushed.lock().unwrap(), [2]);
+        tokio::time::sleep_until(deadline).await;
+        batcher.flush_expired("test");
+        assert_eq!(*flushed.lock().unwrap(), [2, 1]);
+        assert_eq!(batcher.deadline(), None);
+    }
+
+    #[test]
+    fn filter_changes_drop_the_emptied_filter() {
+        use crate::debounce::FilterChanges;
+
+        let request = SubscribeRequestBuilder::new().build();
+        let mut changes = FilterChanges::default();
+        changes.push("Acc1".to_string(), true);
+        changes.push("Acc2".to_string(), true);
+        changes.push("Acc1".to_string(), false);
+        assert!(changes.deadline().is_some());
+        let subscribed = changes.apply(&request).unwrap();
+        assert_eq!(subscribed.accounts["accounts"].account, ["Acc2"]);
+        assert_eq!(changes.deadline(), None);
+
+        // Adding a present account leaves the request unchanged
+        changes.push("Acc2".to_string(), true);
+        assert_eq!(changes.apply(&subscribed), None);
+
+        // An empty account list would match every account
+        changes.push("Acc2".to_string(), false);
+        assert!(changes.apply(&subscribed).unwrap().accounts.is_empty());
+    }
+}
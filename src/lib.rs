@@ -1,128 +1,2311 @@
 
 
 use {
-    futures::{sink::SinkExt, stream::StreamExt}, std::{str::FromStr, time::Duration}, tonic::{metadata::AsciiMetadataValue, transport::Endpoint}, tonic_health::pb::health_client::HealthClient, yellowstone_grpc_client::{GeyserGrpcClient, InterceptorXToken}, yellowstone_grpc_proto::{
+    futures::{channel::mpsc::SendError, future::BoxFuture, sink::{Sink, SinkExt}, stream::{Stream, StreamExt}}, std::{collections::{HashMap, VecDeque}, path::Path, pin::Pin, str::FromStr, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::{Duration, Instant}}, tokio::{sync::{mpsc, watch, Semaphore}, task::{JoinHandle, JoinSet}}, tokio_util::sync::CancellationToken, tonic::{metadata::AsciiMetadataValue, transport::{Channel, Endpoint, Uri}, Code, Status}, tonic_health::pb::health_client::HealthClient, yellowstone_grpc_client::{GeyserGrpcClient, GeyserGrpcClientError, InterceptorXToken}, yellowstone_grpc_proto::{
         geyser::{
-            geyser_client::GeyserClient, subscribe_update::UpdateOneof, SubscribeRequest, SubscribeUpdateTransaction
+            geyser_client::GeyserClient, subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterSlots, SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateBlock, SubscribeUpdateSlot, SubscribeUpdateTransaction
         },
         prelude::SubscribeRequestPing,
     }
 };
 
+#[cfg(feature = "details")]
+mod account;
+mod batch;
+mod bookmark;
+mod config;
+mod connector;
+mod debug;
+mod debounce;
+mod decode;
+mod diff;
+mod error;
+mod events;
+#[cfg(feature = "details")]
+mod fees;
+mod handle;
+mod limits;
+mod middleware;
+mod migration;
+mod ordering;
+#[cfg(feature = "otel")]
+mod otel;
+mod outcome;
+mod pool;
+#[cfg(feature = "proxy")]
+mod proxy;
+mod reconnect;
+mod recording;
+mod request;
+mod router;
+#[cfg(feature = "signal")]
+mod signal;
+mod slots;
+mod split;
+mod stats;
+mod transaction;
+mod watchdog;
+
+pub use {
+    batch::BatchConfig,
+    bookmark::{Bookmark, FileBookmark, MemoryBookmark, StreamPosition},
+    config::ChannelConfig,
+    connector::ConnectionInfo,
+    debounce::FilterDebounce,
+    decode::{decode_batch, AccountDecoder},
+    error::GrpcStreamError,
+    events::{ChannelState, DisconnectReason, Heartbeat, LifecycleEvent, ServerWarning, StreamFailure},
+    handle::{PausePolicy, StreamHandle},
+    limits::account_filter_count,
+    middleware::{MiddlewarePipeline, UpdateMiddleware},
+    ordering::{OrderingViolation, UpdateKind},
+    outcome::{HandlerOutcome, HandlerRetry, RetryExhausted},
+    pool::{SubscriptionHandle, SubscriptionPool, SubscriptionState},
+    reconnect::{apply_jitter, default_retry_policy, BackoffStrategy, EscalationPolicy, MaxAttemptsPolicy, RetryDecision, LONGER_BACKOFF_FACTOR},
+    request::{BlockFilterBuilder, SubscribeRequestBuilder, TransactionFilterBuilder},
+    router::TransactionRouter,
+    slots::SlotTracker,
+    split::SplitStreams,
+    stats::{HandlerHistogram, StreamStats, HANDLER_BUCKETS},
+    transaction::{signature_array, signature_bytes, transaction_signature, transaction_slot, transaction_succeeded},
+    watchdog::ThroughputWatchdog,
+};
+
+use {batch::Batcher, bookmark::Bookmarking, diff::AccountDiff, connector::{ObservedConnections, ObservingConnector}, stats::LastMessage, debounce::FilterChanges, debug::DebugBuffer, handle::Command, migration::{CatchUpState, Dedup, HighWaterKeys, Migration}, ordering::SequenceTracker, outcome::PendingRetry, reconnect::{Escalation, Reconnect}, recording::{Recorder, Replay}, split::SplitSenders};
+
+/// Re-exports of the geyser protocol types
+/// 
+/// Build requests and match on updates through this module rather than depending on
+/// `yellowstone-grpc-proto` directly, which avoids type mismatches between versions.
 pub mod proto {
-    pub use yellowstone_grpc_proto::geyser;
+    pub use yellowstone_grpc_proto::{
+        geyser::{self, subscribe_update},
+        prelude::*,
+        solana::storage::confirmed_block,
+    };
 }
 
+pub use {tonic, yellowstone_grpc_client};
+
+#[cfg(feature = "solana")]
+pub use transaction::transaction_error;
+#[cfg(feature = "details")]
+pub use {
+    account::DecodedAccount,
+    fees::{priority_fee_info, PriorityFeeInfo, DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_COMPUTE_UNIT_LIMIT},
+    transaction::TransactionDetails,
+};
+
 #[cfg(debug_assertions)]
 pub mod helper;
 
 
 pub struct GrpcStreamManager {
     endpoint: String,
+    channel: Channel,
+    x_token: Option<AsciiMetadataValue>,
     client: GeyserGrpcClient<InterceptorXToken>,
-    is_connected: bool,
-    reconnect_attempts: u32,
-    max_reconnect_attempts: u32,
-    reconnect_interval: Duration,
-    tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>
+    client_snapshot: bool,
+    snapshot_requested: bool,
+    config: ChannelConfig,
+    commands_tx: mpsc::UnboundedSender<Command>,
+    commands: mpsc::UnboundedReceiver<Command>,
+    shutdown: CancellationToken,
+    stats: StreamStats,
+    is_connected: Arc<watch::Sender<bool>>,
+    reconnect: Reconnect,
+    tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>,
+    account_handler: Option<Box<dyn Fn(SubscribeUpdateAccount, &str) + Send + Sync>>,
+    block_handler: Option<Box<dyn Fn(SubscribeUpdateBlock, &str) + Send + Sync>>,
+    slot_handler: Option<Box<dyn Fn(SubscribeUpdateSlot, &str) + Send + Sync>>,
+    failed_tx_handler: Option<Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>>,
+    router: Option<TransactionRouter>,
+    skip_snapshot: bool,
+    snapshot_complete_handler: Option<Box<dyn Fn(&str) + Send + Sync>>,
+    snapshot_reconcile_handler: Option<Box<dyn Fn(Vec<String>, &str) + Send + Sync>>,
+    snapshot_accounts: Vec<String>,
+    sequence_tracker: Option<SequenceTracker>,
+    ordering_violation_handler: Option<Box<dyn Fn(OrderingViolation, &str) + Send + Sync>>,
+    throughput_watchdog: Option<ThroughputWatchdog>,
+    slow_handler_threshold: Option<Duration>,
+    max_account_filters: Option<usize>,
+    batcher: Option<Batcher>,
+    account_batcher: Option<Batcher>,
+    pause_policy: PausePolicy,
+    paused: bool,
+    paused_updates: VecDeque<(Vec<String>, UpdateOneof)>,
+    async_tx_handler: Option<Box<dyn Fn(SubscribeUpdateTransaction, String) -> BoxFuture<'static, ()> + Send + Sync>>,
+    async_account_handler: Option<Box<dyn Fn(SubscribeUpdateAccount, String) -> BoxFuture<'static, ()> + Send + Sync>>,
+    handler_permits: Option<Arc<Semaphore>>,
+    pending_handlers: VecDeque<BoxFuture<'static, ()>>,
+    handler_tasks: JoinSet<()>,
+    outcome_tx_handler: Option<Box<dyn Fn(&SubscribeUpdateTransaction, &str) -> HandlerOutcome + Send + Sync>>,
+    outcome_account_handler: Option<Box<dyn Fn(&SubscribeUpdateAccount, &str) -> HandlerOutcome + Send + Sync>>,
+    handler_retry: HandlerRetry,
+    pending_retries: VecDeque<PendingRetry>,
+    stop_requested: bool,
+    bookmark: Option<Bookmarking>,
+    health_supported: bool,
+    middleware: Option<MiddlewarePipeline>,
+    lifecycle_handler: Option<Box<dyn Fn(LifecycleEvent, &str, &str) + Send + Sync>>,
+    channel_probe: Option<Duration>,
+    heartbeat_interval: Option<Duration>,
+    channel_state: ChannelState,
+    debug_buffer: Arc<DebugBuffer>,
+    label: Option<String>,
+    filter_changes: FilterChanges,
+    split_pending: Vec<UpdateOneof>,
+    last_message: Arc<LastMessage>,
+    server_warning_handler: Option<Box<dyn Fn(ServerWarning, &str) + Send + Sync>>,
+    endpoint_resolver: Option<Box<dyn Fn() -> BoxFuture<'static, (String, Option<String>)> + Send + Sync>>,
+    escalation: Option<Escalation>,
+    account_diff: Option<AccountDiff>,
+    observed_connections: ObservedConnections,
+    connection_info: Arc<Mutex<Option<ConnectionInfo>>>,
+    high_water: HighWaterKeys,
 }
 
 impl GrpcStreamManager {
     
-    pub async fn new(endpoint: &str, x_token: Option<String>, tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>) -> Result<GrpcStreamManager, anyhow::Error> {
-        let x_token = if let Some(token) = x_token {
-            Some(AsciiMetadataValue::from_str(token.as_str())?)
-        } else {
-            None
-        };
+    pub async fn new(endpoint: &str, x_token: Option<String>, tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>) -> Result<GrpcStreamManager, GrpcStreamError> {
+        Self::with_config(endpoint, x_token, ChannelConfig::default(), tx_handler).await
+    }
+
+    /// Creates a manager using custom channel settings
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint to connect to
+    /// * `x_token` - Optional authentication token sent as `x-token` metadata
+    /// * `config` - Channel settings such as connect and request timeouts
+    /// * `tx_handler` - Called with every transaction update and the endpoint it was received from,
+    ///   see [`transaction_slot`] for the slot it was observed in
+    pub async fn with_config(endpoint: &str, x_token: Option<String>, config: ChannelConfig, tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>) -> Result<GrpcStreamManager, GrpcStreamError> {
+        let x_token = parse_token(x_token)?;
 
-        let interceptor = InterceptorXToken {
-            x_token: x_token,
-            x_request_snapshot: true,
+        let connect_started = Instant::now();
+        let observed = ObservedConnections::default();
+        let channel = connect_channel_with_retries(endpoint, &config, &observed).await?;
+        let stats = StreamStats {
+            channel_connect: Some(connect_started.elapsed()),
+            ..Default::default()
         };
 
-        let channel = Endpoint::from_shared(endpoint.to_string())?
-            .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(10))
-            .connect()
-            .await
-            .map_err(|e| anyhow::Error::from(e))?;
+        Ok(Self::from_channel(endpoint, x_token, config, channel, observed, stats, tx_handler))
+    }
 
-        let client = GeyserGrpcClient::new(
-            HealthClient::with_interceptor(channel.clone(), interceptor.clone()),
-            GeyserClient::with_interceptor(channel, interceptor),
-        );
+    /// Creates a manager subscribing on an already established channel
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint the channel is connected to
+    /// * `x_token` - Optional authentication token sent as `x-token` metadata
+    /// * `config` - Channel settings such as connect and request timeouts
+    /// * `channel` - The channel to subscribe on
+    /// * `observed` - Where the connections of `channel` are recorded
+    /// * `stats` - The initial stats, carrying the channel connect time if measured
+    /// * `tx_handler` - Called with every transaction update and the endpoint it was received from
+    pub(crate) fn from_channel(
+        endpoint: &str,
+        x_token: Option<AsciiMetadataValue>,
+        config: ChannelConfig,
+        channel: Channel,
+        observed: ObservedConnections,
+        stats: StreamStats,
+        tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>,
+    ) -> GrpcStreamManager {
+        let client = build_client(&channel, x_token.clone(), config.request_snapshot, &config);
+        let (commands_tx, commands) = mpsc::unbounded_channel();
 
-        Ok(GrpcStreamManager {
+        GrpcStreamManager {
             endpoint: endpoint.to_string(),
+            channel,
+            x_token,
             client,
-            is_connected: false,
-            reconnect_attempts: 0,
-            max_reconnect_attempts: 10,
-            reconnect_interval: Duration::from_secs(5),
-            tx_handler: tx_handler
-        })
+            client_snapshot: config.request_snapshot,
+            snapshot_requested: false,
+            config,
+            commands_tx,
+            commands,
+            shutdown: CancellationToken::new(),
+            stats: StreamStats {
+                label: endpoint_host(endpoint).to_string(),
+                ..stats
+            },
+            is_connected: Arc::new(watch::Sender::new(false)),
+            reconnect: Reconnect::default(),
+            tx_handler: tx_handler,
+            account_handler: None,
+            block_handler: None,
+            slot_handler: None,
+            failed_tx_handler: None,
+            router: None,
+            skip_snapshot: false,
+            snapshot_complete_handler: None,
+            snapshot_reconcile_handler: None,
+            snapshot_accounts: Vec::new(),
+            sequence_tracker: None,
+            ordering_violation_handler: None,
+            throughput_watchdog: None,
+            slow_handler_threshold: None,
+            max_account_filters: None,
+            batcher: None,
+            account_batcher: None,
+            pause_policy: PausePolicy::default(),
+            paused: false,
+            paused_updates: VecDeque::new(),
+            async_tx_handler: None,
+            async_account_handler: None,
+            handler_permits: None,
+            pending_handlers: VecDeque::new(),
+            handler_tasks: JoinSet::new(),
+            outcome_tx_handler: None,
+            outcome_account_handler: None,
+            handler_retry: HandlerRetry::default(),
+            pending_retries: VecDeque::new(),
+            stop_requested: false,
+            bookmark: None,
+            health_supported: true,
+            middleware: None,
+            lifecycle_handler: None,
+            channel_probe: None,
+            heartbeat_interval: None,
+            channel_state: ChannelState::Ready,
+            debug_buffer: Arc::default(),
+            label: None,
+            filter_changes: FilterChanges::default(),
+            split_pending: Vec::new(),
+            last_message: Arc::default(),
+            server_warning_handler: None,
+            endpoint_resolver: None,
+            escalation: None,
+            account_diff: None,
+            observed_connections: observed,
+            connection_info: Arc::default(),
+            high_water: HighWaterKeys::default(),
+        }
+    }
+
+    /// Returns a handle for controlling the stream while `connect` is running
+    pub fn handle(&self) -> StreamHandle {
+        StreamHandle {
+            commands: self.commands_tx.clone(),
+            shutdown: self.shutdown.clone(),
+            connected: self.is_connected.subscribe(),
+            debug_buffer: self.debug_buffer.clone(),
+            last_message: self.last_message.clone(),
+            connection_info: self.connection_info.clone(),
+        }
+    }
+
+    /// Returns the underlying geyser client for one-off RPCs
+    /// 
+    /// The client shares the channel with the subscription stream, so unary calls such as
+    /// `get_slot` or `get_latest_blockhash` are multiplexed over the same connection
+    /// instead of opening a new one. The client may be replaced when the stream
+    /// resubscribes, so do not hold on to clones of it.
+    pub fn client_mut(&mut self) -> &mut GeyserGrpcClient<InterceptorXToken> {
+        &mut self.client
+    }
+
+    /// Returns the statistics collected so far
+    pub fn stats(&self) -> &StreamStats {
+        &self.stats
+    }
+
+    /// Sets how many consecutive reconnects are attempted before applying the [`MaxAttemptsPolicy`]
+    /// 
+    /// Setting it to `0` disables reconnection: `connect` runs a single connection, returns
+    /// the first stream error as is and returns `Ok(())` if the server closes the stream.
+    /// 
+    /// # Arguments
+    /// * `attempts` - The maximum number of consecutive reconnect attempts, 10 by default
+    pub fn set_max_reconnect_attempts(&mut self, attempts: u32) {
+        self.reconnect.max_attempts = attempts;
+    }
+
+    /// Sets what happens once `max_reconnect_attempts` consecutive reconnects have failed
+    /// 
+    /// # Arguments
+    /// * `policy` - The policy to apply, [`MaxAttemptsPolicy::Error`] by default
+    pub fn set_max_attempts_policy(&mut self, policy: MaxAttemptsPolicy) {
+        self.reconnect.policy = policy;
+    }
+
+    /// Sets how the delay between reconnect attempts grows
+    /// 
+    /// # Arguments
+    /// * `strategy` - The backoff strategy, linear in steps of 5s up to 25s by default
+    pub fn set_backoff_strategy(&mut self, strategy: BackoffStrategy) {
+        self.reconnect.strategy = strategy;
+    }
+
+    /// Randomizes reconnect delays to avoid synchronized reconnects across clients
+    /// 
+    /// # Arguments
+    /// * `jitter` - The maximum relative deviation of each delay, between `0.0` and `1.0`
+    pub fn set_reconnect_jitter(&mut self, jitter: f64) {
+        self.reconnect.jitter = jitter;
+    }
+
+    /// Sets how subscribe and stream failures are handled per gRPC status code
+    /// 
+    /// The policy applies alike to the subscribe RPC being rejected and to an established
+    /// stream failing. Codes missing from `policy` fall back to [`default_retry_policy`],
+    /// and to [`RetryDecision::Retry`] if not listed there either. Failures other than a
+    /// status returned by the server, such as timeouts or the throughput watchdog, are
+    /// always retried.
+    /// 
+    /// # Arguments
+    /// * `policy` - The decision for each status code to override
+    pub fn set_retry_policy(&mut self, policy: HashMap<Code, RetryDecision>) {
+        let mut merged = default_retry_policy();
+        merged.extend(policy);
+        self.reconnect.retry_policy = merged;
+    }
+
+    /// Bounds the total time spent disconnected before `connect` gives up
+    /// 
+    /// Downtime accumulates across consecutive reconnect attempts, including the failed
+    /// attempts themselves, and resets once a subscription is established again. A
+    /// backoff that would exceed the budget is not started, `connect` returns
+    /// [`GrpcStreamError::MaxDowntime`] instead. Applies in addition to the attempt limit.
+    /// 
+    /// # Arguments
+    /// * `budget` - The maximum cumulative downtime, `None` for no bound
+    pub fn set_max_total_downtime(&mut self, budget: Option<Duration>) {
+        self.reconnect.max_total_downtime = budget;
+    }
+
+    /// Sets the handler invoked for account updates
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with every account update and the endpoint it was received from
+    pub fn set_account_handler(&mut self, handler: Box<dyn Fn(SubscribeUpdateAccount, &str) + Send + Sync>) {
+        self.account_handler = Some(handler);
+    }
+
+    /// Only delivers account updates whose data changed in a way `changed` cares about
+    /// 
+    /// The last data of each account is retained and passed to `changed` together with
+    /// the new data, e.g. to compare only the fields a handler depends on. The first
+    /// update of an account is always delivered. Once `cache_size` accounts are retained,
+    /// the one added first is evicted and its next update is delivered unconditionally.
+    /// 
+    /// # Arguments
+    /// * `changed` - Returns whether the update from the old to the new data is delivered
+    /// * `cache_size` - The maximum number of accounts whose data is retained
+    pub fn set_account_change_filter(&mut self, changed: Box<dyn Fn(&[u8], &[u8]) -> bool + Send + Sync>, cache_size: usize) {
+        self.account_diff = Some(AccountDiff::new(changed, cache_size));
+    }
+
+    /// Retains the most recent updates for debugging
+    /// 
+    /// The updates are available through [`recent_updates`](Self::recent_updates) and
+    /// [`StreamHandle::recent_updates`], e.g. to dump them after an ordering violation.
+    /// Retention is bounded to `size` updates and costs nothing when disabled.
+    /// 
+    /// # Arguments
+    /// * `size` - The number of updates to retain, `None` to disable
+    pub fn set_debug_buffer(&mut self, size: Option<usize>) {
+        self.debug_buffer.set_capacity(size.unwrap_or(0));
+    }
+
+    /// Returns when the last update was received, `None` if none was yet
+    /// 
+    /// Pings and pongs are not counted, so a connection that is alive but silent shows
+    /// up as stale.
+    pub fn last_message_at(&self) -> Option<Instant> {
+        self.last_message.at()
+    }
+
+    /// Returns the time since the last update was received, `None` if none was yet
+    pub fn time_since_last_message(&self) -> Option<Duration> {
+        self.last_message.at().map(|at| at.elapsed())
+    }
+
+    /// Returns the connection the subscription is established on, `None` while disconnected
+    /// 
+    /// Refreshed on every subscribe, see [`ConnectionInfo`] for what is available.
+    pub fn connection_info(&self) -> Option<ConnectionInfo> {
+        match *self.is_connected.borrow() {
+            true => *self.connection_info.lock().unwrap(),
+            false => None,
+        }
+    }
+
+    /// Returns the updates retained by the debug buffer, oldest first
+    pub fn recent_updates(&self) -> Vec<SubscribeUpdate> {
+        self.debug_buffer.recent()
+    }
+
+    /// Sets how account filter changes from [`StreamHandle::add_account_filter`] and
+    /// [`StreamHandle::remove_account_filter`] are coalesced
+    /// 
+    /// # Arguments
+    /// * `debounce` - The quiet period, batch size and target filter, see [`FilterDebounce`]
+    pub fn set_filter_debounce(&mut self, debounce: FilterDebounce) {
+        self.filter_changes.config = debounce;
+    }
+
+    /// Sets a resolver choosing the endpoint and token before every (re)connect
+    /// 
+    /// Use this to integrate service discovery or to rotate tokens. When the resolved
+    /// endpoint or token differs from the current one, a new channel is connected before
+    /// subscribing; a failure to connect counts as a failed reconnect attempt.
+    /// 
+    /// # Arguments
+    /// * `resolver` - Returns the endpoint and optional `x-token` to connect with
+    pub fn set_endpoint_resolver(&mut self, resolver: Box<dyn Fn() -> BoxFuture<'static, (String, Option<String>)> + Send + Sync>) {
+        self.endpoint_resolver = Some(resolver);
+    }
+
+    /// Moves reconnects on to fallback endpoints once the current one keeps failing
+    /// 
+    /// The rotation starts with the manager's current endpoint followed by `fallbacks`.
+    /// After `attempts_per_endpoint` consecutive failed attempts, reconnects go to the next
+    /// endpoint with the attempt counter reset. `connect` fails with
+    /// [`GrpcStreamError::EndpointsExhausted`] once the rotation was cycled through
+    /// `full_cycles_before_fatal` times without a successful subscribe, which also resets
+    /// the cycle count. Keep `attempts_per_endpoint` below the max reconnect attempts, which
+    /// are otherwise reached first. Not meant to be combined with an endpoint resolver.
+    /// 
+    /// # Arguments
+    /// * `fallbacks` - The endpoints and optional `x-token`s to fall back to, in order
+    /// * `policy` - The attempts per endpoint and the cycles before giving up
+    pub fn set_endpoint_escalation(&mut self, fallbacks: Vec<(String, Option<String>)>, policy: EscalationPolicy) -> Result<(), GrpcStreamError> {
+        let mut endpoints = vec![(self.endpoint.clone(), self.x_token.clone())];
+        for (endpoint, x_token) in fallbacks {
+            endpoints.push((endpoint, parse_token(x_token)?));
+        }
+        self.escalation = Some(Escalation {
+            policy,
+            endpoints,
+            index: 0,
+            cycles: 0,
+        });
+        Ok(())
+    }
+
+    /// Sets a label identifying this subscription in logs
+    /// 
+    /// Log lines of the manager are prefixed with the label, except those logged while
+    /// the constructor connects, which use the endpoint host. The label is also reported
+    /// in [`StreamStats::label`], in heartbeats and to the lifecycle handler. Update
+    /// handlers still receive the endpoint; capture the label in the handler to attach it
+    /// there as well.
+    /// 
+    /// # Arguments
+    /// * `label` - The label, e.g. `liquidations`, `None` to use the endpoint host
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+        self.stats.label = self.label().to_string();
+    }
+
+    /// Returns the label of this subscription, the endpoint host unless set explicitly
+    pub fn label(&self) -> &str {
+        match &self.label {
+            Some(label) => label,
+            None => endpoint_host(&self.endpoint),
+        }
+    }
+
+    /// Sets the handler invoked for in-band notices from the server
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with every [`ServerWarning`] and the endpoint it was received from
+    pub fn set_server_warning_handler(&mut self, handler: Box<dyn Fn(ServerWarning, &str) + Send + Sync>) {
+        self.server_warning_handler = Some(handler);
+    }
+
+    /// Sets the handler invoked on connection lifecycle changes
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with every [`LifecycleEvent`], the endpoint it concerns and the
+    ///   [`label`](Self::label) of the manager
+    pub fn set_lifecycle_handler(&mut self, handler: Box<dyn Fn(LifecycleEvent, &str, &str) + Send + Sync>) {
+        self.lifecycle_handler = Some(handler);
+    }
+
+    /// Enables periodic probing of the channel's connectivity
+    /// 
+    /// A stream without traffic only notices a dead connection on its next read. The probe
+    /// sends a health check every `period`, reports transitions as
+    /// [`LifecycleEvent::ChannelStateChanged`] and reconnects once the channel stops
+    /// answering within the request timeout. Servers without the health service still
+    /// count as ready as long as they respond.
+    /// 
+    /// # Arguments
+    /// * `period` - How often to probe, `None` to disable
+    pub fn set_channel_probe(&mut self, period: Option<Duration>) {
+        self.channel_probe = period;
+    }
+
+    /// Emits a [`LifecycleEvent::Heartbeat`] at a fixed interval while connected
+    /// 
+    /// Heartbeats are sent regardless of traffic, so quiet periods still confirm the
+    /// stream is alive along with the number of updates received since the last one.
+    /// 
+    /// # Arguments
+    /// * `interval` - The time between heartbeats, `None` to disable
+    pub fn set_heartbeat_interval(&mut self, interval: Option<Duration>) {
+        self.heartbeat_interval = interval;
+    }
+
+    /// Sets the middleware every update passes through before reaching the handlers
+    /// 
+    /// Middleware runs after snapshot detection and ordering validation, so those still
+    /// see every update received from the stream.
+    /// 
+    /// # Arguments
+    /// * `pipeline` - The stages to apply, see [`MiddlewarePipeline`]
+    pub fn set_middleware(&mut self, pipeline: MiddlewarePipeline) {
+        self.middleware = Some(pipeline);
+    }
+
+    /// Sets a separate handler for failed transactions
+    /// 
+    /// Transactions whose status meta carries an error go to this handler instead of
+    /// `tx_handler` or the async transaction handler. Transactions without status meta
+    /// stay with the regular handlers, and transactions matched by the router are still
+    /// dispatched by the router.
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with every failed transaction and the endpoint it was received from
+    pub fn set_failed_transaction_handler(&mut self, handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>) {
+        self.failed_tx_handler = Some(handler);
+    }
+
+    /// Sets the handler invoked for block updates
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with every block update and the endpoint it was received from
+    pub fn set_block_handler(&mut self, handler: Box<dyn Fn(SubscribeUpdateBlock, &str) + Send + Sync>) {
+        self.block_handler = Some(handler);
+    }
+
+    /// Sets the handler invoked for slot updates
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with every slot update and the endpoint it was received from
+    pub fn set_slot_handler(&mut self, handler: Box<dyn Fn(SubscribeUpdateSlot, &str) + Send + Sync>) {
+        self.slot_handler = Some(handler);
+    }
+
+    /// Sets a router dispatching transactions to per-filter handlers
+    /// 
+    /// Transactions that do not match any of the router's filters are passed to `tx_handler`.
+    /// Use [`TransactionRouter::subscribe_request`] to build the matching request.
+    /// 
+    /// # Arguments
+    /// * `router` - The router holding the named filters and their handlers
+    pub fn set_router(&mut self, router: TransactionRouter) {
+        self.router = Some(router);
+    }
+
+    /// Suppresses handler invocation while the server replays the initial snapshot
+    /// 
+    /// The snapshot is replayed on every (re)connect, so updates are skipped until the
+    /// first live update of each connection.
+    /// 
+    /// # Arguments
+    /// * `skip` - Whether snapshot updates should be dropped instead of handled
+    pub fn set_skip_snapshot(&mut self, skip: bool) {
+        self.skip_snapshot = skip;
+    }
+
+    /// Sets the callback invoked once the snapshot phase of a connection ends
+    /// 
+    /// The server does not send an explicit end-of-snapshot marker. Snapshot updates are
+    /// account updates flagged with `is_startup`, so the phase is considered complete on
+    /// the first update of any other kind (pings and pongs excluded). If the server sends
+    /// no snapshot at all, the callback fires on the first live update.
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with the endpoint whenever a connection switches to live data
+    pub fn set_snapshot_complete_handler(&mut self, handler: Box<dyn Fn(&str) + Send + Sync>) {
+        self.snapshot_complete_handler = Some(handler);
+    }
+
+    /// Sets the callback receiving every account seen in a completed snapshot
+    /// 
+    /// Fires together with the snapshot complete callback, but only for connections that
+    /// requested a snapshot. Accounts missing from the list no longer match the
+    /// subscription, so local state for them can be pruned. Snapshot accounts are
+    /// collected even when [`set_skip_snapshot`](Self::set_skip_snapshot) drops them.
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with the base58 encoded pubkeys of the snapshot and the endpoint
+    pub fn set_snapshot_reconcile_handler(&mut self, handler: Box<dyn Fn(Vec<String>, &str) + Send + Sync>) {
+        self.snapshot_reconcile_handler = Some(handler);
     }
 
     /// Establishes connection and handles the subscription stream
     /// 
+    /// Runs until the stream fails permanently or [`StreamHandle::shutdown`] is called.
+    /// The request, including its `ping` configuration, is replayed unchanged on every
+    /// reconnect until replaced through [`StreamHandle::update_subscription`].
+    /// 
+    /// # Cancel safety
+    /// 
+    /// The returned future can be dropped at any await point, e.g. as a `tokio::select!`
+    /// branch. Pings are answered on the read loop itself, so no background task is left
+    /// behind: dropping the future closes the subscription stream, any pending migration,
+    /// and marks the handle as disconnected. The channel stays usable and the manager can
+    /// be connected again. Updates still held by the batchers or the pause buffer, and
+    /// async handler invocations still waiting for a permit, are delivered on the next
+    /// `connect`; async handlers already spawned run to completion unless the manager is
+    /// dropped. Dropping the future of [`record_to`](Self::record_to) does not flush the
+    /// recording, so the updates still buffered by its writer are lost. No
+    /// [`LifecycleEvent::Disconnected`] is reported for a dropped future.
+    /// 
     /// # Arguments
     /// * `request` - The subscription request containing account filters and other parameters
-    pub async fn connect(&mut self, request: SubscribeRequest) -> Result<(), anyhow::Error> {
-        let request = request.clone();
-        
-        loop {
-            let (mut subscribe_tx, mut stream) = self.client.subscribe_with_request(Some(request.clone())).await?;
-
-            self.is_connected = true;
-            self.reconnect_attempts = 0;
-
-            while let Some(message) = stream.next().await {
-            match message {
-                Ok(msg) => {
-                    match msg.update_oneof {
-                        Some(UpdateOneof::Transaction(tx)) => {
-                            self.tx_handler.as_ref()(tx, &self.endpoint);
-                        }
-                        Some(UpdateOneof::Ping(_)) => {
-                            subscribe_tx
-                                .send(SubscribeRequest {
-                                    ping: Some(SubscribeRequestPing { id: 1 }),
-                                    ..Default::default()
-                                })
-                                .await?;
-                        }
-                        Some(UpdateOneof::Pong(_)) => {} // Ignore pong responses
-                        _ => {}
-                    }
-                },
-                Err(err) => {
-                    log::error!("Error: {:?}", err);
-                    drop(subscribe_tx);
-                    drop(stream);
-                    self.is_connected = false;
-                    self.reconnect(request.clone()).await?;
-                    break;
+    pub async fn connect(&mut self, request: SubscribeRequest) -> Result<(), GrpcStreamError> {
+        self.run(request, Consumer::Handlers).await
+    }
+
+    /// Subscribes and folds every update into an accumulator
+    /// 
+    /// Updates are passed to `f` instead of the configured handlers. Reconnection and pings
+    /// are handled as in `connect`, and the final accumulator is returned on shutdown.
+    /// 
+    /// # Arguments
+    /// * `request` - The subscription request containing account filters and other parameters
+    /// * `init` - The initial accumulator value
+    /// * `f` - Combines the accumulator with the next update
+    pub async fn fold<Acc, F>(&mut self, request: SubscribeRequest, init: Acc, mut f: F) -> Result<Acc, GrpcStreamError>
+    where
+        Acc: Send,
+        F: FnMut(Acc, UpdateOneof) -> Acc + Send,
+    {
+        let mut acc = Some(init);
+        let mut apply = |update: UpdateOneof| {
+            if let Some(value) = acc.take() {
+                acc = Some(f(value, update));
+            }
+        };
+
+        self.run(request, Consumer::Custom(&mut apply)).await?;
+        Ok(acc.expect("accumulator is restored after every update"))
+    }
+
+    /// Watches a single account, the simplest way to follow one account's changes
+    /// 
+    /// Connects a manager with default settings, subscribes to the account and invokes
+    /// `handler` on every update on a spawned task, reconnecting as `connect` does. Stop
+    /// it through the returned handle; the join handle completes with the result of
+    /// `connect`.
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint to connect to
+    /// * `x_token` - Optional authentication token sent as `x-token` metadata
+    /// * `pubkey` - The base58 encoded address of the account
+    /// * `commitment` - The commitment level updates are delivered at
+    /// * `handler` - Called with every update of the account and the endpoint it was received from
+    pub async fn watch_account(
+        endpoint: &str,
+        x_token: Option<String>,
+        pubkey: &str,
+        commitment: CommitmentLevel,
+        handler: Box<dyn Fn(SubscribeUpdateAccount, &str) + Send + Sync>,
+    ) -> Result<(StreamHandle, JoinHandle<Result<(), GrpcStreamError>>), GrpcStreamError> {
+        let mut manager = GrpcStreamManager::new(endpoint, x_token, Box::new(|_, _| {})).await?;
+        manager.set_account_handler(handler);
+
+        let filter = SubscribeRequestFilterAccounts {
+            account: vec![pubkey.to_string()],
+            ..Default::default()
+        };
+        let request = SubscribeRequestBuilder::new()
+            .accounts("account", filter)
+            .commitment(commitment)
+            .build();
+
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(request).await });
+        Ok((handle, task))
+    }
+
+    /// Follows slot progression through a slots-only subscription
+    /// 
+    /// Connects a manager with default settings and invokes `handler` on every slot update
+    /// on a spawned task, reconnecting as `connect` does. The returned tracker keeps the
+    /// highest slot seen and stops the subscription through its handle.
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint to connect to
+    /// * `x_token` - Optional authentication token sent as `x-token` metadata
+    /// * `commitment` - Only slots reaching this commitment level are delivered
+    /// * `interslot_updates` - Whether to also receive the intermediate statuses of each slot
+    /// * `handler` - Called with every slot update and the endpoint it was received from
+    pub async fn track_slots(
+        endpoint: &str,
+        x_token: Option<String>,
+        commitment: CommitmentLevel,
+        interslot_updates: bool,
+        handler: Box<dyn Fn(SubscribeUpdateSlot, &str) + Send + Sync>,
+    ) -> Result<SlotTracker, GrpcStreamError> {
+        let mut manager = GrpcStreamManager::new(endpoint, x_token, Box::new(|_, _| {})).await?;
+        let highest = Arc::new(AtomicU64::new(0));
+        let observed = highest.clone();
+        manager.set_slot_handler(Box::new(move |update, endpoint| {
+            observed.fetch_max(update.slot, Ordering::Relaxed);
+            handler(update, endpoint);
+        }));
+
+        // Fields the pinned proto adds beyond these keep their defaults
+        let mut filter = SubscribeRequestFilterSlots {
+            filter_by_commitment: Some(true),
+            ..Default::default()
+        };
+        filter.interslot_updates = Some(interslot_updates);
+        let request = SubscribeRequestBuilder::new()
+            .slots("slots", filter)
+            .commitment(commitment)
+            .build();
+
+        let handle = manager.handle();
+        let task = tokio::spawn(async move { manager.connect(request).await });
+        Ok(SlotTracker { handle, task, highest })
+    }
+
+    /// Connects for a fixed duration, then shuts down and returns the collected stats
+    /// 
+    /// Reconnects within the window as `connect` does. Once `duration` has elapsed the
+    /// stream stops as [`StreamHandle::shutdown`] does, including during a reconnect
+    /// backoff, and running async handlers are awaited. As with a shutdown, the manager
+    /// cannot be connected again afterwards. Dropping the future before the deadline
+    /// leaves the manager as dropping the future of `connect` does, without a pending
+    /// shutdown.
+    /// 
+    /// # Arguments
+    /// * `request` - The subscription request containing account filters and other parameters
+    /// * `duration` - How long to stream for
+    pub async fn run_for(&mut self, request: SubscribeRequest, duration: Duration) -> Result<StreamStats, GrpcStreamError> {
+        let shutdown = self.shutdown.clone();
+        let result = {
+            let connect = self.connect(request);
+            tokio::pin!(connect);
+            tokio::select! {
+                result = &mut connect => result,
+                _ = tokio::time::sleep(duration) => {
+                    shutdown.cancel();
+                    connect.await
                 }
             }
-        }}
+        };
+        while self.handler_tasks.join_next().await.is_some() {}
+        self.stats.in_flight_handlers = 0;
+        result.map(|()| self.stats.clone())
+    }
 
+    /// Connects until SIGINT or SIGTERM is received, then shuts down gracefully
+    /// 
+    /// A signal stops the stream as [`StreamHandle::shutdown`] does: pending batches are
+    /// flushed, and running async handlers are awaited before the final stats are
+    /// returned. Only SIGINT (Ctrl-C) is handled on platforms other than Unix. Signals
+    /// are only listened for while the future is polled.
+    /// 
+    /// # Arguments
+    /// * `request` - The subscription request containing account filters and other parameters
+    #[cfg(feature = "signal")]
+    pub async fn run_until_ctrl_c(&mut self, request: SubscribeRequest) -> Result<StreamStats, GrpcStreamError> {
+        let shutdown = self.shutdown.clone();
+        let label = self.label().to_string();
+        let result = {
+            let connect = self.connect(request);
+            tokio::pin!(connect);
+            tokio::select! {
+                result = &mut connect => result,
+                received = signal::shutdown_signal() => {
+                    match received {
+                        Ok(()) => {
+                            log::info!("[{}] Received shutdown signal, draining", label);
+                            shutdown.cancel();
+                        }
+                        Err(err) => log::error!("[{}] Failed to listen for shutdown signals: {}", label, err),
+                    }
+                    connect.await
+                }
+            }
+        };
+        while self.handler_tasks.join_next().await.is_some() {}
+        self.stats.in_flight_handlers = 0;
+        result.map(|()| self.stats.clone())
     }
 
-    /// Attempts to reconnect when the connection is lost
+    /// Subscribes on a spawned task, delivering updates to a bounded channel per type
+    /// 
+    /// Transactions, accounts and slots are sent to their own channel instead of the
+    /// configured handlers; other update types are discarded, and the channels of types
+    /// the request does not subscribe to stay empty. A full channel pauses reading from
+    /// the stream until the receiver catches up, which holds back the other channels as
+    /// well. A shutdown stops waiting for a full channel, discarding the updates not sent
+    /// yet. Updates for a dropped receiver are discarded. Obtain a [`handle`](Self::handle)
+    /// beforehand to control the spawned subscription.
     /// 
     /// # Arguments
-    /// * `request` - The original subscription request to reestablish the connection
-    async fn reconnect(&mut self, request: SubscribeRequest) -> Result<(), anyhow::Error> {
-        if self.reconnect_attempts >= self.max_reconnect_attempts {
-            return Err(anyhow::anyhow!("Max reconnection attempts reached"));
+    /// * `request` - The subscription request containing account filters and other parameters
+    /// * `capacity` - The capacity of each channel, `0` is treated as `1`
+    pub fn connect_split(mut self, request: SubscribeRequest, capacity: usize) -> SplitStreams {
+        let capacity = capacity.max(1);
+        let (transactions, transactions_rx) = mpsc::channel(capacity);
+        let (accounts, accounts_rx) = mpsc::channel(capacity);
+        let (slots, slots_rx) = mpsc::channel(capacity);
+        let senders = SplitSenders { transactions, accounts, slots };
+
+        let task = tokio::spawn(async move { self.run(request, Consumer::Split(&senders)).await });
+        SplitStreams {
+            transactions: transactions_rx,
+            accounts: accounts_rx,
+            slots: slots_rx,
+            task,
         }
+    }
 
-        self.reconnect_attempts += 1;
+    /// Subscribes and writes every update to a file for later replay
+    /// 
+    /// Updates are recorded with their filters and `created_at` timestamp instead of being
+    /// passed to the configured handlers. Reconnection and pings are handled as in
+    /// `connect`. Runs until shutdown or a failure, including failing to write the file.
+    /// The file is flushed when this returns, not when the future is dropped.
+    /// 
+    /// # Arguments
+    /// * `path` - The file to record to, truncated if it exists
+    /// * `request` - The subscription request containing account filters and other parameters
+    pub async fn record_to(&mut self, path: impl AsRef<Path>, request: SubscribeRequest) -> Result<(), GrpcStreamError> {
+        let mut recorder = Recorder::create(path.as_ref()).await?;
+        let result = self.run(request, Consumer::Record(&mut recorder)).await;
+        recorder.finish().await?;
+        result
+    }
 
-        let backoff = self.reconnect_interval * std::cmp::min(self.reconnect_attempts, 5);
-        tokio::time::sleep(backoff).await;
+    /// Replays a file written by [`record_to`](Self::record_to) into the configured handlers
+    /// 
+    /// Updates go through the same pipeline as live ones, including routing, batching,
+    /// snapshot handling and ordering validation.
+    /// 
+    /// # Arguments
+    /// * `path` - The recording to replay
+    /// * `preserve_timing` - Whether to reproduce the original spacing of updates based on their `created_at`
+    pub async fn replay_from(&mut self, path: impl AsRef<Path>, preserve_timing: bool) -> Result<(), GrpcStreamError> {
+        let mut replay = Replay::open(path.as_ref()).await?;
+        let mut in_snapshot = true;
+        if let Some(tracker) = &mut self.sequence_tracker {
+            *tracker = SequenceTracker::default();
+        }
 
-        Box::pin(self.connect(request)).await
+        // Recorded positions must not overwrite the position of the live stream
+        let bookmark = self.bookmark.take();
+        let replayed = async {
+            while let Some(msg) = replay.next(preserve_timing).await? {
+                if let Some(update) = msg.update_oneof {
+                    self.handle_update(&msg.filters, update, &mut in_snapshot, &mut Consumer::Handlers);
+                    self.spawn_pending_handlers().await;
+                    self.retry_pending_handlers().await;
+                    if self.stop_requested {
+                        break;
+                    }
+                }
+            }
+            self.flush_batch();
+            Ok::<_, GrpcStreamError>(())
+        }
+        .await;
+        self.bookmark = bookmark;
+        replayed
     }
+
+    /// Runs the subscription loop, passing updates to `consumer`
+    /// 
+    /// # Arguments
+    /// * `request` - The subscription request containing account filters and other parameters
+    /// * `consumer` - Where updates are delivered
+    async fn run(&mut self, request: SubscribeRequest, mut consumer: Consumer<'_>) -> Result<(), GrpcStreamError> {
+        let _guard = DisconnectGuard(self.is_connected.clone());
+        let mut request = request;
+        limits::check_account_filters(&request, self.max_account_filters)?;
+        self.stop_requested = false;
+        if let Some(bookmark) = &mut self.bookmark {
+            request.from_slot = bookmark.start(request.from_slot)?;
+        }
+        let mut subscribed = false;
+        // GOAWAYs answered by an immediate resubscribe since the last update
+        let mut go_aways = 0;
+
+        loop {
+            if self.shutdown.is_cancelled() {
+                return Ok(());
+            }
+
+            while let Ok(command) = self.commands.try_recv() {
+                match command {
+                    Command::RequestSnapshot => self.snapshot_requested = true,
+                    Command::UpdateSubscription(update) => match limits::check_account_filters(&update, self.max_account_filters) {
+                        Ok(()) => request = update,
+                        Err(err) => log::error!("[{}] Subscription update rejected: {}", self.label(), err),
+                    },
+                    Command::AccountFilter { account, add } => self.filter_changes.push(account, add),
+                    Command::MaxReconnectAttempts(attempts) => self.reconnect.max_attempts = attempts,
+                    Command::ReconnectInterval(interval) => self.reconnect.set_interval(interval),
+                    Command::BackoffStrategy(strategy) => self.reconnect.strategy = strategy,
+                    Command::Pause => self.paused = true,
+                    Command::Resume => self.resume(&mut consumer),
+                    Command::Migrate { endpoint, x_token } => {
+                        // Nothing is streaming, so the endpoint can be switched directly
+                        match connect_endpoint(&endpoint, x_token, &self.config, &self.observed_connections).await {
+                            Ok((channel, x_token)) => {
+                                log::info!("[{}] Switched from {} to {}", self.label(), self.endpoint, endpoint);
+                                self.client = build_client(&channel, x_token.clone(), self.client_snapshot, &self.config);
+                                self.set_endpoint(endpoint);
+                                self.channel = channel;
+                                self.x_token = x_token;
+                            }
+                            Err(err) => log::error!("[{}] Migration to {} failed: {}", self.label(), endpoint, err),
+                        }
+                    }
+                }
+            }
+            // Not streaming, so pending filter changes go into the next subscribe
+            if let Some(update) = self.filter_changes.apply(&request) {
+                match limits::check_account_filters(&update, self.max_account_filters) {
+                    Ok(()) => request = update,
+                    Err(err) => log::error!("[{}] Account filter changes rejected: {}", self.label(), err),
+                }
+            }
+
+            if let Err(err) = self.resolve_endpoint().await {
+                log::error!("[{}] Endpoint resolution failed: {}", self.label(), err);
+                if self.reconnect.disabled() {
+                    return Err(err);
+                }
+                let label = self.label().to_string();
+                let waited = self.reconnect.wait(&self.shutdown, &label).await;
+                self.stats.current_backoff = self.reconnect.last_backoff;
+                waited?;
+                continue;
+            }
+
+            let snapshot = self.config.request_snapshot || self.snapshot_requested;
+            if snapshot != self.client_snapshot {
+                self.client = build_client(&self.channel, self.x_token.clone(), snapshot, &self.config);
+                self.client_snapshot = snapshot;
+            }
+
+            // Resubscribes resume after the handled updates, the first subscribe keeps the start slot
+            if let Some(slot) = self.bookmark.as_ref().and_then(Bookmarking::resume_slot).filter(|_| subscribed) {
+                request.from_slot = Some(slot);
+            }
+
+            let subscribe_started = Instant::now();
+            let (mut subscribe_tx, mut stream) = match subscribe(&mut self.client, &request, self.config.subscribe_timeout).await {
+                Ok(subscription) => subscription,
+                Err(err) => {
+                    if let Some(rejection) = reconnect::failure_status(&err).and_then(limits::filter_limit_rejection) {
+                        return Err(rejection);
+                    }
+                    log::error!("[{}] Subscribe failed: {:?}", self.label(), err);
+                    self.recover(err).await?;
+                    continue;
+                }
+            };
+            if self.stats.last_subscribe.is_some() {
+                self.stats.reconnects += 1;
+            }
+            self.stats.last_subscribe = Some(subscribe_started.elapsed());
+            subscribed = true;
+            self.stats.last_first_update = None;
+            self.snapshot_requested = false;
+
+            self.set_connected();
+            self.set_channel_state(ChannelState::Ready);
+            self.reconnect.reset();
+            if let Some(escalation) = &mut self.escalation {
+                escalation.cycles = 0;
+            }
+            self.snapshot_accounts.clear();
+            let mut in_snapshot = true;
+            if let Some(tracker) = &mut self.sequence_tracker {
+                *tracker = SequenceTracker::default();
+            }
+
+            let mut watchdog = self.throughput_watchdog.map(|watchdog| {
+                let mut interval = tokio::time::interval(watchdog.window);
+                interval.reset();
+                interval
+            });
+            let mut probe = self.channel_probe.map(|period| {
+                let mut interval = tokio::time::interval(period);
+                interval.reset();
+                interval
+            });
+            let mut heartbeat = self.heartbeat_interval.map(|period| {
+                let mut interval = tokio::time::interval(period);
+                interval.reset();
+                interval
+            });
+            let mut window_updates: u64 = 0;
+            let mut heartbeat_updates: u64 = 0;
+            let mut migration: Option<Migration> = None;
+            let mut dedup: Option<Dedup> = None;
+
+            // `Ok` resubscribes immediately, `Err` goes through the reconnect backoff
+            let ended = loop {
+                self.spawn_pending_handlers().await;
+                self.retry_pending_handlers().await;
+                if self.stop_requested {
+                    log::info!("[{}] Handler requested stop", self.label());
+                    self.flush_batch();
+                    self.commit_bookmark();
+                    self.set_disconnected(DisconnectReason::Shutdown);
+                    return Ok(());
+                }
+                if let Consumer::Split(senders) = &consumer {
+                    for update in self.split_pending.drain(..) {
+                        tokio::select! {
+                            () = senders.send(update) => {}
+                            _ = self.shutdown.cancelled() => break,
+                        }
+                    }
+                }
+
+                let message = tokio::select! {
+                    message = stream.next() => message,
+                    _ = self.shutdown.cancelled() => {
+                        self.flush_batch();
+                        self.commit_bookmark();
+                        self.set_disconnected(DisconnectReason::Shutdown);
+                        return Ok(());
+                    }
+                    _ = sleep_until(self.batch_deadline()) => {
+                        self.flush_expired_batches();
+                        continue;
+                    }
+                    _ = sleep_until(self.filter_changes.deadline()) => {
+                        let Some(update) = self.filter_changes.apply(&request) else {
+                            continue;
+                        };
+                        if let Err(err) = limits::check_account_filters(&update, self.max_account_filters) {
+                            log::error!("[{}] Account filter changes rejected: {}", self.label(), err);
+                        } else {
+                            // Kept even if unsent, the resubscribe then carries the change
+                            let sent = subscribe_tx.send(update.clone()).await;
+                            request = update;
+                            if let Err(err) = sent {
+                                break Err(err.into());
+                            }
+                        }
+                        continue;
+                    }
+                    Some(command) = self.commands.recv() => match command {
+                        Command::RequestSnapshot => {
+                            log::info!("[{}] Snapshot requested, resubscribing to {}", self.label(), self.endpoint);
+                            self.snapshot_requested = true;
+                            break Ok(DisconnectReason::Resubscribe);
+                        }
+                        Command::UpdateSubscription(update) => {
+                            if let Err(err) = limits::check_account_filters(&update, self.max_account_filters) {
+                                log::error!("[{}] Subscription update rejected: {}", self.label(), err);
+                            } else if update == request {
+                                log::info!("[{}] Subscription unchanged, skipping update", self.label());
+                            } else {
+                                // Kept even if unsent, the resubscribe then carries the update
+                                let sent = subscribe_tx.send(update.clone()).await;
+                                request = update;
+                                if let Err(err) = sent {
+                                    break Err(err.into());
+                                }
+                            }
+                            continue;
+                        }
+                        Command::AccountFilter { account, add } => {
+                            self.filter_changes.push(account, add);
+                            continue;
+                        }
+                        Command::MaxReconnectAttempts(attempts) => {
+                            self.reconnect.max_attempts = attempts;
+                            continue;
+                        }
+                        Command::ReconnectInterval(interval) => {
+                            self.reconnect.set_interval(interval);
+                            continue;
+                        }
+                        Command::BackoffStrategy(strategy) => {
+                            self.reconnect.strategy = strategy;
+                            continue;
+                        }
+                        Command::Pause => {
+                            self.paused = true;
+                            continue;
+                        }
+                        Command::Resume => {
+                            self.resume(&mut consumer);
+                            continue;
+                        }
+                        Command::Migrate { endpoint, x_token } => {
+                            match start_migration(&endpoint, x_token, &request, &self.high_water, &self.config, &self.observed_connections).await {
+                                Ok(pending) => {
+                                    log::info!("[{}] Migrating from {} to {}", self.label(), self.endpoint, endpoint);
+                                    migration = Some(pending);
+                                }
+                                Err(err) => log::error!("[{}] Migration to {} failed: {}", self.label(), endpoint, err),
+                            }
+                            continue;
+                        }
+                    },
+                    message = migration::next(&mut migration) => {
+                        let Some(pending) = migration.as_mut() else {
+                            continue;
+                        };
+                        let update = match message {
+                            Some(Ok(update)) => update,
+                            Some(Err(err)) => {
+                                log::error!("[{}] Migration to {} failed: {}", self.label(), pending.endpoint, err);
+                                migration = None;
+                                continue;
+                            }
+                            None => {
+                                log::error!("[{}] Migration to {} failed: stream closed", self.label(), pending.endpoint);
+                                migration = None;
+                                continue;
+                            }
+                        };
+
+                        match update.update_oneof {
+                            Some(UpdateOneof::Ping(_)) => {
+                                if let Err(err) = pending.subscribe_tx.send(ping_request()).await {
+                                    log::error!("[{}] Migration to {} failed: {}", self.label(), pending.endpoint, err);
+                                    migration = None;
+                                }
+                                continue;
+                            }
+                            Some(UpdateOneof::Pong(_)) | None => continue,
+                            Some(_) => {}
+                        }
+                        match pending.catch_up.buffer(update) {
+                            CatchUpState::Behind => continue,
+                            CatchUpState::CaughtUp => {}
+                            CatchUpState::Overflowed => {
+                                log::error!(
+                                    "[{}] Migration to {} failed: not caught up after buffering {} updates",
+                                    self.label(),
+                                    pending.endpoint,
+                                    migration::MAX_BUFFERED
+                                );
+                                migration = None;
+                                continue;
+                            }
+                        }
+
+                        // The new stream is past the current high-water slot, cut over to it
+                        let mut pending = migration.take().expect("migration is pending");
+                        let (buffered, overlap) = pending.catch_up.cutover();
+                        log::info!("[{}] Migrated from {} to {}", self.label(), self.endpoint, pending.endpoint);
+                        subscribe_tx = pending.subscribe_tx;
+                        stream = pending.stream;
+                        self.set_endpoint(pending.endpoint);
+                        self.channel = pending.channel;
+                        self.x_token = pending.x_token;
+                        self.client = pending.client;
+                        self.client_snapshot = false;
+                        // Still connected, this only picks up the new connection info
+                        self.set_connected();
+
+                        for msg in buffered {
+                            if msg.update_oneof.as_ref().is_none_or(|update| overlap.contains(update)) {
+                                continue;
+                            }
+                            if let Consumer::Record(recorder) = &mut consumer {
+                                recorder.write(&msg).await?;
+                            }
+                            if let Some(update) = msg.update_oneof {
+                                self.stats.observe_slot(&update);
+                                self.high_water.observe(&update);
+                                self.handle_update(&msg.filters, update, &mut in_snapshot, &mut consumer);
+                            }
+                        }
+                        dedup = Some(overlap);
+                        continue;
+                    }
+                    _ = tick(&mut probe) => {
+                        let state = match self.health_check().await {
+                            Ok(()) => ChannelState::Ready,
+                            // The server answered, only without a serving status
+                            Err(GrpcStreamError::Client(GeyserGrpcClientError::TonicStatus(status)))
+                                if status.code() != Code::Unavailable => ChannelState::Ready,
+                            Err(_) => ChannelState::TransientFailure,
+                        };
+                        self.set_channel_state(state);
+                        if state == ChannelState::TransientFailure {
+                            break Err(GrpcStreamError::ChannelUnavailable);
+                        }
+                        continue;
+                    }
+                    _ = tick(&mut heartbeat) => {
+                        self.emit(LifecycleEvent::Heartbeat(Heartbeat {
+                            label: self.label().to_string(),
+                            messages: std::mem::take(&mut heartbeat_updates),
+                            slot: self.stats.highest_slot,
+                            reconnects: self.stats.reconnects,
+                        }));
+                        continue;
+                    }
+                    _ = tick(&mut watchdog) => {
+                        let Some(limits) = self.throughput_watchdog else {
+                            continue;
+                        };
+                        let rate = window_updates as f64 / limits.window.as_secs_f64();
+                        window_updates = 0;
+                        if rate < limits.min_rate {
+                            break Err(GrpcStreamError::LowThroughput { rate });
+                        }
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    if self.reconnect.disabled() {
+                        self.commit_bookmark();
+                        self.set_disconnected(DisconnectReason::StreamClosed);
+                        return Ok(());
+                    }
+                    break Ok(DisconnectReason::StreamClosed);
+                };
+
+                if self.stats.last_first_update.is_none() {
+                    self.stats.last_first_update = Some(subscribe_started.elapsed());
+                }
+
+                match message {
+                    Ok(msg) => {
+                        match msg.update_oneof {
+                            Some(UpdateOneof::Ping(_)) => {
+                                if let Err(err) = subscribe_tx.send(ping_request()).await {
+                                    break Err(err.into());
+                                }
+                            }
+                            Some(UpdateOneof::Pong(_)) => {} // Ignore pong responses
+                            Some(update) => {
+                                if let Some(overlap) = &dedup {
+                                    if overlap.expired_by(&update) {
+                                        dedup = None;
+                                    } else if overlap.contains(&update) {
+                                        continue;
+                                    }
+                                }
+                                if let Some(pending) = &mut migration {
+                                    pending.catch_up.observe(&update);
+                                }
+
+                                if let Consumer::Record(recorder) = &mut consumer {
+                                    let recorded = SubscribeUpdate {
+                                        filters: msg.filters.clone(),
+                                        update_oneof: Some(update.clone()),
+                                        created_at: msg.created_at,
+                                    };
+                                    recorder.write(&recorded).await?;
+                                }
+                                self.debug_buffer.push(&msg.filters, &update, msg.created_at);
+
+                                window_updates += 1;
+                                heartbeat_updates += 1;
+                                self.stats.observe_slot(&update);
+                                self.high_water.observe(&update);
+                                self.last_message.touch();
+                                go_aways = 0;
+                                self.handle_update(&msg.filters, update, &mut in_snapshot, &mut consumer);
+                            }
+                            None => {
+                                if let Some(handler) = &self.server_warning_handler {
+                                    handler(ServerWarning::UnknownUpdate { filters: msg.filters }, &self.endpoint);
+                                }
+                            }
+                        }
+                    },
+                    Err(err) => {
+                        if let Some(rejection) = limits::filter_limit_rejection(&err) {
+                            let failure = StreamFailure::capture(&GrpcStreamError::Status(err));
+                            self.set_disconnected(DisconnectReason::StreamError(failure));
+                            return Err(rejection);
+                        }
+                        if reconnect::is_graceful_go_away(&err) && go_aways < reconnect::MAX_GO_AWAY_RESUBSCRIBES {
+                            if self.reconnect.disabled() {
+                                log::info!("[{}] {} sent GOAWAY", self.label(), self.endpoint);
+                                self.commit_bookmark();
+                                self.set_disconnected(DisconnectReason::GoAway);
+                                return Ok(());
+                            }
+                            // A graceful drain, the channel reconnects to a fresh connection
+                            go_aways += 1;
+                            log::info!("[{}] {} sent GOAWAY, resubscribing", self.label(), self.endpoint);
+                            break Ok(DisconnectReason::GoAway);
+                        }
+                        break Err(err.into());
+                    }
+                }
+            };
+
+            drop(subscribe_tx);
+            drop(stream);
+            drop(migration);
+            self.flush_batch();
+            self.commit_bookmark();
+
+            match ended {
+                Ok(reason) => self.set_disconnected(reason),
+                Err(err) => {
+                    log::error!("[{}] Error: {:?}", self.label(), err);
+                    self.set_disconnected(DisconnectReason::StreamError(StreamFailure::capture(&err)));
+                    self.recover(err).await?;
+                }
+            }
+        }
+    }
+
+    /// Enables timing of handler invocations
+    /// 
+    /// Execution times are recorded per update kind in [`StreamStats::handler_timings`],
+    /// and a warning is logged for every invocation slower than `threshold`. Handlers run
+    /// on the read loop, so slow handlers delay all following updates.
+    /// 
+    /// # Arguments
+    /// * `threshold` - The duration above which a handler call is logged, `None` to disable timing
+    pub fn set_slow_handler_threshold(&mut self, threshold: Option<Duration>) {
+        self.slow_handler_threshold = threshold;
+    }
+
+    /// Sets the maximum number of account filter pubkeys a request may contain
+    /// 
+    /// Requests exceeding the limit fail with [`GrpcStreamError::FilterLimitExceeded`]
+    /// before being sent, and updates exceeding it are dropped with an error log. Server
+    /// rejections due to a limit are reported with the same error regardless of this
+    /// setting. Oversized filter sets are not split across subscriptions.
+    /// 
+    /// # Arguments
+    /// * `limit` - The maximum number of pubkeys across all account filters, `None` for no limit
+    pub fn set_max_account_filters(&mut self, limit: Option<usize>) {
+        self.max_account_filters = limit;
+    }
+
+    /// Delivers updates in batches instead of one by one
+    /// 
+    /// Updates are buffered until `batch_size` is reached or `batch_timeout` has passed
+    /// since the first buffered update, then passed to `handler` instead of the per-kind
+    /// handlers. Pending updates are also flushed on disconnect and shutdown.
+    /// 
+    /// # Arguments
+    /// * `config` - The size and timeout limits of a batch
+    /// * `handler` - Called with every batch and the endpoint it was received from
+    pub fn set_batch_handler(&mut self, config: BatchConfig, handler: Box<dyn Fn(Vec<UpdateOneof>, &str) + Send + Sync>) {
+        self.batcher = Some(Batcher::new(config, handler));
+    }
+
+    /// Decodes account updates in batches before handing them to `handler`
+    /// 
+    /// Account updates are buffered per `config` and decoded with [`decode_batch`], in
+    /// parallel when the `rayon` feature is enabled, while other updates keep going to
+    /// their handlers. Decoding runs on the read loop, which waits for the whole batch.
+    /// Has no effect if [`set_batch_handler`](Self::set_batch_handler) is also used.
+    /// 
+    /// # Arguments
+    /// * `config` - The size and timeout limits of a batch
+    /// * `decoder` - Decodes each account update
+    /// * `handler` - Called with the decoded batch and the endpoint it was received from
+    pub fn set_account_batch_decoder<D: AccountDecoder>(&mut self, config: BatchConfig, decoder: D, handler: Box<dyn Fn(Vec<D::Output>, &str) + Send + Sync>) {
+        let on_batch = move |batch: Vec<UpdateOneof>, endpoint: &str| {
+            let accounts: Vec<SubscribeUpdateAccount> = batch
+                .into_iter()
+                .filter_map(|update| match update {
+                    UpdateOneof::Account(account) => Some(account),
+                    _ => None,
+                })
+                .collect();
+            handler(decode_batch(&decoder, &accounts), endpoint);
+        };
+        self.account_batcher = Some(Batcher::new(config, Box::new(on_batch)));
+    }
+
+    /// Sets an async handler for transaction updates, used instead of `tx_handler`
+    /// 
+    /// Each invocation is spawned as a separate task, see
+    /// [`set_max_concurrent_handlers`](Self::set_max_concurrent_handlers) to bound them.
+    /// Tasks still running are aborted when the manager is dropped.
+    /// 
+    /// # Arguments
+    /// * `handler` - Returns the future processing a transaction received from the given endpoint
+    pub fn set_async_transaction_handler(&mut self, handler: Box<dyn Fn(SubscribeUpdateTransaction, String) -> BoxFuture<'static, ()> + Send + Sync>) {
+        self.async_tx_handler = Some(handler);
+    }
+
+    /// Sets an async handler for account updates, used instead of the account handler
+    /// 
+    /// # Arguments
+    /// * `handler` - Returns the future processing an account update received from the given endpoint
+    pub fn set_async_account_handler(&mut self, handler: Box<dyn Fn(SubscribeUpdateAccount, String) -> BoxFuture<'static, ()> + Send + Sync>) {
+        self.async_account_handler = Some(handler);
+    }
+
+    /// Sets a transaction handler that reports whether the update was processed, used instead of `tx_handler`
+    /// 
+    /// Returning [`HandlerOutcome::Retry`] invokes the handler again with the same update
+    /// as configured by [`set_handler_retry`](Self::set_handler_retry), e.g. after a
+    /// transient database error. [`HandlerOutcome::Stop`] stops the manager.
+    /// 
+    /// # Arguments
+    /// * `handler` - Processes a transaction received from the given endpoint
+    pub fn set_transaction_outcome_handler(&mut self, handler: Box<dyn Fn(&SubscribeUpdateTransaction, &str) -> HandlerOutcome + Send + Sync>) {
+        self.outcome_tx_handler = Some(handler);
+    }
+
+    /// Sets an account handler that reports whether the update was processed, used instead of the account handler
+    /// 
+    /// See [`set_transaction_outcome_handler`](Self::set_transaction_outcome_handler).
+    /// 
+    /// # Arguments
+    /// * `handler` - Processes an account update received from the given endpoint
+    pub fn set_account_outcome_handler(&mut self, handler: Box<dyn Fn(&SubscribeUpdateAccount, &str) -> HandlerOutcome + Send + Sync>) {
+        self.outcome_account_handler = Some(handler);
+    }
+
+    /// Sets how updates are retried when an outcome handler returns [`HandlerOutcome::Retry`]
+    /// 
+    /// # Arguments
+    /// * `retry` - The retry count, delay and what happens once retries are used up
+    pub fn set_handler_retry(&mut self, retry: HandlerRetry) {
+        self.handler_retry = retry;
+    }
+
+    /// Records the position of handled updates in `bookmark` for at-least-once delivery
+    /// 
+    /// On `connect` a request without `from_slot` starts at the slot of the stored
+    /// position. An explicit `from_slot` is kept for the first subscribe and replaces the
+    /// stored position, which may then move backwards. Resubscribes resume from the last
+    /// committed slot, so updates of that slot are delivered again.
+    /// 
+    /// Updates count as handled once their sync handler returned, or once their outcome
+    /// handler returned [`HandlerOutcome::Continue`]; updates without a handler for their
+    /// kind are not. Updates passed to async or batch handlers are not tracked, and neither
+    /// are the updates of `fold`, `record`, `split` or `replay_from`. The highest handled
+    /// position is committed every `commit_every` handled updates and whenever the stream
+    /// ends, but not while an update waits for an outcome handler retry. Failed commits
+    /// are logged and retried with the next one.
+    /// 
+    /// # Arguments
+    /// * `bookmark` - Where positions are stored, e.g. a [`FileBookmark`]
+    /// * `commit_every` - Number of handled updates between commits, `1` to commit after each
+    pub fn set_bookmark(&mut self, bookmark: Box<dyn Bookmark>, commit_every: u32) {
+        self.bookmark = Some(Bookmarking::new(bookmark, commit_every));
+    }
+
+    /// Bounds the number of async handler invocations running at the same time
+    /// 
+    /// Once the limit is reached the read loop waits for a running handler to finish,
+    /// applying backpressure to the stream. A shutdown ends the wait, leaving the
+    /// invocations still waiting for a permit unstarted. The number of running handlers
+    /// is reported in [`StreamStats::in_flight_handlers`].
+    /// 
+    /// # Arguments
+    /// * `limit` - The maximum number of concurrent handler futures, `None` for no bound; `0` is treated as `1`, which would otherwise stall the stream
+    pub fn set_max_concurrent_handlers(&mut self, limit: Option<usize>) {
+        self.handler_permits = limit.map(|limit| Arc::new(Semaphore::new(limit.max(1))));
+    }
+
+    /// Sets what happens to updates received while paused through [`StreamHandle::pause`]
+    /// 
+    /// # Arguments
+    /// * `policy` - The policy to apply, buffering up to 10000 updates by default
+    pub fn set_pause_policy(&mut self, policy: PausePolicy) {
+        self.pause_policy = policy;
+    }
+
+    /// Sets a watchdog that reconnects when throughput stays below a minimum rate
+    /// 
+    /// This catches connections that keep answering pings but stop delivering data. Only
+    /// enable it for subscriptions that are expected to receive steady traffic.
+    /// 
+    /// # Arguments
+    /// * `watchdog` - The minimum rate and measurement window, `None` to disable
+    pub fn set_throughput_watchdog(&mut self, watchdog: Option<ThroughputWatchdog>) {
+        self.throughput_watchdog = watchdog;
+    }
+
+    /// Checks the health of the server using the configured request timeout
+    /// 
+    /// Servers that do not implement the gRPC health service are assumed to be serving,
+    /// see [`health_supported`](Self::health_supported).
+    pub async fn health_check(&mut self) -> Result<(), GrpcStreamError> {
+        let result = config::with_timeout(Some(self.config.request_timeout), self.client.health_check())
+            .await
+            .map_err(|_| GrpcStreamError::Timeout("health check"))?;
+
+        match result {
+            Ok(_) => {
+                self.health_supported = true;
+                Ok(())
+            }
+            Err(GeyserGrpcClientError::TonicStatus(status)) if status.code() == Code::Unimplemented => {
+                if self.health_supported {
+                    log::info!("[{}] {} does not implement the health service, assuming serving", self.label(), self.endpoint);
+                }
+                self.health_supported = false;
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns whether the server implements the gRPC health service
+    /// 
+    /// Reported as supported until a health check found otherwise.
+    pub fn health_supported(&self) -> bool {
+        self.health_supported
+    }
+
+    /// Enables ordering validation and sets the callback invoked on violations
+    /// 
+    /// Slots are checked by slot number, accounts by `write_version` and transactions by
+    /// their index within the slot. Only regressions are reported, see [`OrderingViolation`].
+    /// Tracking restarts on every (re)connect.
+    /// 
+    /// # Arguments
+    /// * `handler` - Called with the violation and the endpoint it was observed on
+    pub fn set_ordering_violation_handler(&mut self, handler: Box<dyn Fn(OrderingViolation, &str) + Send + Sync>) {
+        self.sequence_tracker = Some(SequenceTracker::default());
+        self.ordering_violation_handler = Some(handler);
+    }
+
+    /// Dispatches a single update to the consumer
+    /// 
+    /// # Arguments
+    /// * `filters` - The names of the filters the update matched
+    /// * `update` - The update received from the stream
+    /// * `in_snapshot` - Whether the current connection is still replaying the snapshot
+    /// * `consumer` - Where the update is delivered
+    fn handle_update(&mut self, filters: &[String], update: UpdateOneof, in_snapshot: &mut bool, consumer: &mut Consumer<'_>) {
+        if let Some(violation) = self.sequence_tracker.as_mut().and_then(|tracker| tracker.check(&update)) {
+            if let Some(handler) = &self.ordering_violation_handler {
+                handler(violation, &self.endpoint);
+            }
+        }
+
+        if *in_snapshot {
+            let is_startup = matches!(&update, UpdateOneof::Account(account) if account.is_startup);
+            if is_startup {
+                if let UpdateOneof::Account(SubscribeUpdateAccount { account: Some(info), .. }) = &update {
+                    if self.snapshot_reconcile_handler.is_some() {
+                        self.snapshot_accounts.push(bs58::encode(&info.pubkey).into_string());
+                    }
+                }
+                if self.skip_snapshot {
+                    return;
+                }
+            } else {
+                *in_snapshot = false;
+                if let Some(handler) = &self.snapshot_complete_handler {
+                    handler(&self.endpoint);
+                }
+                let seen = std::mem::take(&mut self.snapshot_accounts);
+                match &self.snapshot_reconcile_handler {
+                    Some(handler) if self.client_snapshot => handler(seen, &self.endpoint),
+                    _ => {}
+                }
+            }
+        }
+
+        let update = match &self.middleware {
+            Some(pipeline) => match pipeline.process(update) {
+                Some(update) => update,
+                None => return,
+            },
+            None => update,
+        };
+
+        if self.paused {
+            match self.pause_policy {
+                PausePolicy::Buffer { max } if self.paused_updates.len() < max => {
+                    self.paused_updates.push_back((filters.to_vec(), update));
+                    if self.paused_updates.len() == max {
+                        log::warn!("[{}] Pause buffer full, dropping further updates from {}", self.label(), self.endpoint);
+                    }
+                }
+                PausePolicy::Buffer { .. } | PausePolicy::Drop => {}
+            }
+            return;
+        }
+
+        self.deliver(filters, update, consumer);
+    }
+
+    /// Resumes delivery, flushing updates buffered while paused
+    /// 
+    /// # Arguments
+    /// * `consumer` - Where the buffered updates are delivered
+    fn resume(&mut self, consumer: &mut Consumer<'_>) {
+        self.paused = false;
+        while let Some((filters, update)) = self.paused_updates.pop_front() {
+            self.deliver(&filters, update, consumer);
+        }
+    }
+
+    /// Delivers an update to the consumer, through the batcher if batching is enabled
+    /// 
+    /// # Arguments
+    /// * `filters` - The names of the filters the update matched
+    /// * `update` - The update to deliver
+    /// * `consumer` - Where the update is delivered
+    fn deliver(&mut self, filters: &[String], update: UpdateOneof, consumer: &mut Consumer<'_>) {
+        match consumer {
+            Consumer::Handlers => {}
+            Consumer::Custom(consume) => {
+                consume(update);
+                return;
+            }
+            Consumer::Record(_) => return,
+            Consumer::Split(_) => {
+                // Sent from the read loop, which can wait on full channels
+                self.split_pending.push(update);
+                return;
+            }
+        }
+
+        if let UpdateOneof::Account(account) = &update {
+            if let Some(diff) = &mut self.account_diff {
+                if !diff.check(account) {
+                    return;
+                }
+            }
+        }
+
+        if let Some(batcher) = &mut self.batcher {
+            batcher.push(update, &self.endpoint);
+            return;
+        }
+        if matches!(update, UpdateOneof::Account(_)) {
+            if let Some(batcher) = &mut self.account_batcher {
+                batcher.push(update, &self.endpoint);
+                return;
+            }
+        }
+
+        match self.slow_handler_threshold {
+            Some(threshold) => {
+                let kind = UpdateKind::of(&update);
+                let started = Instant::now();
+                let pending = self.dispatch(filters, update);
+                let elapsed = started.elapsed();
+                self.pending_handlers.extend(pending);
+
+                match kind {
+                    Some(kind) => {
+                        self.stats.handler_timings.entry(kind).or_default().record(elapsed);
+                        if elapsed > threshold {
+                            log::warn!("[{}] {:?} handler took {:?} on {}", self.label(), kind, elapsed, self.endpoint);
+                        }
+                    }
+                    None if elapsed > threshold => log::warn!("[{}] Handler took {:?} on {}", self.label(), elapsed, self.endpoint),
+                    None => {}
+                }
+            }
+            None => {
+                let pending = self.dispatch(filters, update);
+                self.pending_handlers.extend(pending);
+            }
+        }
+    }
+
+    /// Waits out the backoff after a failure, escalating to the next endpoint if due
+    /// 
+    /// A failure to connect to the next endpoint counts as a failed attempt on it.
+    /// 
+    /// # Arguments
+    /// * `err` - The error the subscribe RPC or the stream failed with
+    async fn recover(&mut self, mut err: GrpcStreamError) -> Result<(), GrpcStreamError> {
+        loop {
+            if let Some(escalation) = &mut self.escalation {
+                if escalation.due(self.reconnect.attempts) {
+                    escalation.advance()?;
+                    self.reconnect.attempts = 0;
+                }
+            }
+            if !self.reconnect.disabled() && self.reconnect.classify(&err) != RetryDecision::Fatal {
+                self.emit(LifecycleEvent::Reconnecting {
+                    attempt: self.reconnect.attempts + 1,
+                    failure: StreamFailure::capture(&err),
+                });
+            }
+            let label = self.label().to_string();
+            let recovered = self.reconnect.recover(err, &self.shutdown, &label).await;
+            self.stats.current_backoff = self.reconnect.last_backoff;
+            recovered?;
+            if self.shutdown.is_cancelled() {
+                return Ok(());
+            }
+
+            let Some((endpoint, x_token)) = self.escalation.as_ref().map(Escalation::current).cloned() else {
+                return Ok(());
+            };
+            if endpoint == self.endpoint {
+                return Ok(());
+            }
+            match connect_channel(&endpoint, &self.config, &self.observed_connections).await {
+                Ok(channel) => {
+                    log::warn!("[{}] Escalating from {} to {}", self.label(), self.endpoint, endpoint);
+                    self.client = build_client(&channel, x_token.clone(), self.client_snapshot, &self.config);
+                    self.set_endpoint(endpoint);
+                    self.channel = channel;
+                    self.x_token = x_token;
+                    return Ok(());
+                }
+                Err(connect_err) => {
+                    log::error!("[{}] Connecting to {} failed: {}", self.label(), endpoint, connect_err);
+                    err = connect_err;
+                }
+            }
+        }
+    }
+
+    /// Switches to the endpoint returned by the endpoint resolver, if it changed
+    async fn resolve_endpoint(&mut self) -> Result<(), GrpcStreamError> {
+        let Some(resolver) = &self.endpoint_resolver else {
+            return Ok(());
+        };
+        let (endpoint, x_token) = resolver().await;
+        let x_token = parse_token(x_token)?;
+        if endpoint == self.endpoint && x_token == self.x_token {
+            return Ok(());
+        }
+
+        let channel = connect_channel(&endpoint, &self.config, &self.observed_connections).await?;
+        log::info!("[{}] Resolved endpoint {}, switching from {}", self.label(), endpoint, self.endpoint);
+        self.client = build_client(&channel, x_token.clone(), self.client_snapshot, &self.config);
+        self.set_endpoint(endpoint);
+        self.channel = channel;
+        self.x_token = x_token;
+        Ok(())
+    }
+
+    /// Switches the endpoint, keeping the label in the stats current
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint to stream from
+    fn set_endpoint(&mut self, endpoint: String) {
+        self.endpoint = endpoint;
+        self.stats.label = self.label().to_string();
+    }
+
+    /// Publishes the established subscription and its connection info, reporting the change
+    fn set_connected(&self) {
+        let info = self.observed_connections.lock().unwrap().get(&self.endpoint).copied();
+        *self.connection_info.lock().unwrap() = info;
+        if !self.is_connected.send_replace(true) {
+            self.emit(LifecycleEvent::Connected);
+        }
+    }
+
+    /// Publishes the end of the subscription, reporting why it ended if it was established
+    /// 
+    /// # Arguments
+    /// * `reason` - Why the subscription ended
+    fn set_disconnected(&self, reason: DisconnectReason) {
+        *self.connection_info.lock().unwrap() = None;
+        if self.is_connected.send_replace(false) {
+            self.emit(LifecycleEvent::Disconnected { reason });
+        }
+    }
+
+    /// Records the probed channel state, reporting transitions to the lifecycle handler
+    /// 
+    /// # Arguments
+    /// * `state` - The state observed by the latest probe or subscribe
+    fn set_channel_state(&mut self, state: ChannelState) {
+        let from = std::mem::replace(&mut self.channel_state, state);
+        if from != state {
+            self.emit(LifecycleEvent::ChannelStateChanged { from, to: state });
+        }
+    }
+
+    /// Passes an event to the lifecycle handler, if any
+    /// 
+    /// # Arguments
+    /// * `event` - The event to report
+    fn emit(&self, event: LifecycleEvent) {
+        if let Some(handler) = &self.lifecycle_handler {
+            handler(event, &self.endpoint, self.label());
+        }
+    }
+
+    /// Passes a transaction to the outcome handler, queueing a retry if it asks for one
+    /// 
+    /// # Arguments
+    /// * `tx` - The transaction to process
+    /// * `attempt` - The number of retries so far
+    fn retry_transaction(&mut self, tx: SubscribeUpdateTransaction, attempt: u32) {
+        let Some(handler) = &self.outcome_tx_handler else {
+            return;
+        };
+        let outcome = handler(&tx, &self.endpoint);
+        if outcome == HandlerOutcome::Continue {
+            self.advance_bookmark(Some(StreamPosition::of_transaction(&tx)));
+        }
+        if self.apply_outcome(outcome, attempt) {
+            self.pending_retries.push_back(PendingRetry::Transaction { tx, attempt: attempt + 1 });
+        }
+    }
+
+    /// Passes an account update to the outcome handler, queueing a retry if it asks for one
+    /// 
+    /// # Arguments
+    /// * `account` - The account update to process
+    /// * `attempt` - The number of retries so far
+    fn retry_account(&mut self, account: SubscribeUpdateAccount, attempt: u32) {
+        let Some(handler) = &self.outcome_account_handler else {
+            return;
+        };
+        let outcome = handler(&account, &self.endpoint);
+        if outcome == HandlerOutcome::Continue {
+            self.advance_bookmark(Some(StreamPosition::of_account(&account)));
+        }
+        if self.apply_outcome(outcome, attempt) {
+            self.pending_retries.push_back(PendingRetry::Account { account, attempt: attempt + 1 });
+        }
+    }
+
+    /// Acts on a handler outcome, returning whether the update is to be retried
+    /// 
+    /// # Arguments
+    /// * `outcome` - What the handler returned
+    /// * `attempt` - The number of retries so far
+    fn apply_outcome(&mut self, outcome: HandlerOutcome, attempt: u32) -> bool {
+        match outcome {
+            HandlerOutcome::Continue => false,
+            HandlerOutcome::Stop => {
+                self.stop_requested = true;
+                false
+            }
+            HandlerOutcome::Retry if attempt < self.handler_retry.max_retries => true,
+            HandlerOutcome::Retry => {
+                match self.handler_retry.on_exhausted {
+                    RetryExhausted::Drop => log::warn!("[{}] Dropping update after {} handler retries", self.label(), attempt),
+                    RetryExhausted::Stop => {
+                        log::error!("[{}] Stopping after {} handler retries", self.label(), attempt);
+                        self.stop_requested = true;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// Re-invokes outcome handlers for the updates they asked to retry
+    /// 
+    /// Waits out the retry delay before each attempt, returning early on shutdown or once
+    /// a handler asked to stop.
+    async fn retry_pending_handlers(&mut self) {
+        while let Some(pending) = self.pending_retries.pop_front() {
+            if self.stop_requested || self.shutdown.is_cancelled() {
+                self.pending_retries.clear();
+                // Positions handled after the dropped updates would skip them on resume
+                if let Some(bookmark) = &mut self.bookmark {
+                    bookmark.discard();
+                }
+                return;
+            }
+            let attempt = match &pending {
+                PendingRetry::Transaction { attempt, .. } | PendingRetry::Account { attempt, .. } => *attempt,
+            };
+            reconnect::sleep(self.handler_retry.delay * attempt, &self.shutdown).await;
+            match pending {
+                PendingRetry::Transaction { tx, attempt } => self.retry_transaction(tx, attempt),
+                PendingRetry::Account { account, attempt } => self.retry_account(account, attempt),
+            }
+        }
+    }
+
+    /// Spawns the async handler invocations queued by the last updates
+    /// 
+    /// Waits for a permit before each spawn when the concurrency is bounded. A handler is
+    /// only dequeued once its permit is acquired, so dropping the wait or shutting down
+    /// keeps it queued.
+    async fn spawn_pending_handlers(&mut self) {
+        while !self.pending_handlers.is_empty() {
+            match &self.handler_permits {
+                Some(permits) => {
+                    let permit = tokio::select! {
+                        permit = permits.clone().acquire_owned() => permit.expect("handler semaphore is never closed"),
+                        _ = self.shutdown.cancelled() => break,
+                    };
+                    let handler = self.pending_handlers.pop_front().expect("handler is queued");
+                    self.handler_tasks.spawn(async move {
+                        handler.await;
+                        drop(permit);
+                    });
+                }
+                None => {
+                    let handler = self.pending_handlers.pop_front().expect("handler is queued");
+                    self.handler_tasks.spawn(handler);
+                }
+            }
+        }
+
+        while self.handler_tasks.try_join_next().is_some() {}
+        self.stats.in_flight_handlers = self.handler_tasks.len();
+    }
+
+    /// Records a handled update in the bookmark
+    /// 
+    /// # Arguments
+    /// * `position` - The position of the handled update, `None` if it has none
+    fn advance_bookmark(&mut self, position: Option<StreamPosition>) {
+        let (Some(bookmark), Some(position)) = (&mut self.bookmark, position) else {
+            return;
+        };
+        // An update waiting for a retry was not handled yet, so nothing past it is committed
+        let hold = !self.pending_retries.is_empty();
+        if let Err(err) = bookmark.advance(position, hold) {
+            log::error!("[{}] Bookmark commit failed: {}", self.label(), err);
+        }
+    }
+
+    /// Commits the highest handled position to the bookmark, unless retries are pending
+    fn commit_bookmark(&mut self) {
+        let Some(bookmark) = &mut self.bookmark else {
+            return;
+        };
+        if !self.pending_retries.is_empty() {
+            return;
+        }
+        if let Err(err) = bookmark.commit() {
+            log::error!("[{}] Bookmark commit failed: {}", self.label(), err);
+        }
+    }
+
+    /// Hands any buffered updates to the batch handlers
+    fn flush_batch(&mut self) {
+        for batcher in [&mut self.batcher, &mut self.account_batcher].into_iter().flatten() {
+            batcher.flush(&self.endpoint);
+        }
+    }
+
+    /// Hands buffered updates whose batch timeout passed to the batch handlers
+    fn flush_expired_batches(&mut self) {
+        for batcher in [&mut self.batcher, &mut self.account_batcher].into_iter().flatten() {
+            batcher.flush_expired(&self.endpoint);
+        }
+    }
+
+    /// Returns the earliest deadline of the pending batches
+    fn batch_deadline(&self) -> Option<tokio::time::Instant> {
+        [&self.batcher, &self.account_batcher]
+            .into_iter()
+            .flatten()
+            .filter_map(Batcher::deadline)
+            .min()
+    }
+
+    /// Passes an update to the handler configured for its kind
+    /// 
+    /// # Arguments
+    /// * `filters` - The names of the filters the update matched
+    /// * `update` - The update to dispatch
+    /// 
+    /// Returns the future to spawn if the update goes to an async handler.
+    fn dispatch(&mut self, filters: &[String], update: UpdateOneof) -> Option<BoxFuture<'static, ()>> {
+        let position = StreamPosition::of(&update);
+        let handled = match update {
+            UpdateOneof::Transaction(tx) => {
+                let unrouted = match &self.router {
+                    Some(router) => router.dispatch(filters, tx, &self.endpoint),
+                    None => Some(tx),
+                };
+                let unrouted = match (unrouted, &self.failed_tx_handler) {
+                    (Some(tx), Some(handler)) if transaction::transaction_failed(&tx) => {
+                        handler(tx, &self.endpoint);
+                        None
+                    }
+                    (unrouted, _) => unrouted,
+                };
+                let Some(tx) = unrouted else {
+                    // Taken by a route or the failed transaction handler
+                    self.advance_bookmark(position);
+                    return None;
+                };
+                if self.outcome_tx_handler.is_some() {
+                    self.retry_transaction(tx, 0);
+                    return None;
+                }
+                if let Some(handler) = &self.async_tx_handler {
+                    return Some(handler(tx, self.endpoint.clone()));
+                }
+                self.tx_handler.as_ref()(tx, &self.endpoint);
+                true
+            }
+            UpdateOneof::Account(account) => {
+                if self.outcome_account_handler.is_some() {
+                    self.retry_account(account, 0);
+                    return None;
+                }
+                if let Some(handler) = &self.async_account_handler {
+                    return Some(handler(account, self.endpoint.clone()));
+                }
+                let Some(handler) = &self.account_handler else {
+                    return None;
+                };
+                handler(account, &self.endpoint);
+                true
+            }
+            UpdateOneof::Block(block) => {
+                let Some(handler) = &self.block_handler else {
+                    return None;
+                };
+                handler(block, &self.endpoint);
+                true
+            }
+            UpdateOneof::Slot(slot) => {
+                let Some(handler) = &self.slot_handler else {
+                    return None;
+                };
+                handler(slot, &self.endpoint);
+                true
+            }
+            _ => false,
+        };
+        if handled {
+            self.advance_bookmark(position);
+        }
+        None
+    }
+}
+
+/// Waits for the next tick of an optional interval, never completing if it is `None`
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Sleeps until an optional deadline, never completing if it is `None`
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Where the connect loop delivers updates
+enum Consumer<'a> {
+    /// The handlers configured on the manager
+    Handlers,
+    /// A caller-provided closure receiving every update
+    Custom(&'a mut (dyn FnMut(UpdateOneof) + Send)),
+    /// A recording receiving every update with its metadata, see `record_to`
+    Record(&'a mut Recorder),
+    /// Typed channels, see `connect_split`
+    Split(&'a SplitSenders),
+}
+
+/// Returns the host part of an endpoint URI, the whole endpoint if it has none
+/// 
+/// # Arguments
+/// * `endpoint` - The gRPC endpoint
+fn endpoint_host(endpoint: &str) -> &str {
+    let authority = endpoint.split_once("://").map_or(endpoint, |(_, rest)| rest);
+    let authority = authority.split(['/', '?']).next().unwrap_or(authority);
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+/// Marks the stream as disconnected when `run` ends, including when its future is dropped
+struct DisconnectGuard(Arc<watch::Sender<bool>>);
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        self.0.send_replace(false);
+    }
+}
+
+/// Sink half of an established subscription
+pub(crate) type SubscribeSink = Pin<Box<dyn Sink<SubscribeRequest, Error = SendError> + Send>>;
+
+/// Stream half of an established subscription
+pub(crate) type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+/// Parses the authentication token into a metadata value
+/// 
+/// # Arguments
+/// * `x_token` - Optional authentication token sent as `x-token` metadata
+fn parse_token(x_token: Option<String>) -> Result<Option<AsciiMetadataValue>, GrpcStreamError> {
+    x_token
+        .map(|token| AsciiMetadataValue::from_str(token.as_str()).map_err(GrpcStreamError::InvalidToken))
+        .transpose()
+}
+
+/// Parses an endpoint URL, requiring an `http` or `https` scheme
+/// 
+/// # Arguments
+/// * `endpoint` - The gRPC endpoint to connect to
+fn parse_endpoint(endpoint: &str) -> Result<Endpoint, GrpcStreamError> {
+    let invalid = |reason: String| GrpcStreamError::InvalidEndpoint {
+        endpoint: endpoint.to_string(),
+        reason,
+    };
+    let uri: Uri = endpoint.parse().map_err(|err| invalid(format!("{}", err)))?;
+    match uri.scheme_str() {
+        Some("http" | "https") => Ok(Endpoint::from(uri)),
+        Some(scheme) => Err(invalid(format!("unsupported scheme {}, expected http or https", scheme))),
+        None => Err(invalid("missing http:// or https:// scheme".to_string())),
+    }
+}
+
+/// Establishes a channel to an endpoint
+/// 
+/// # Arguments
+/// * `endpoint` - The gRPC endpoint to connect to
+/// * `config` - Channel settings such as the connect timeout
+/// * `observed` - Where the established connections are recorded
+async fn connect_channel(endpoint: &str, config: &ChannelConfig, observed: &ObservedConnections) -> Result<Channel, GrpcStreamError> {
+    let builder = parse_endpoint(endpoint)?.connect_timeout(config.connect_timeout);
+    #[cfg(feature = "proxy")]
+    if let Some(proxy) = proxy::proxy_for(builder.uri(), config) {
+        log::debug!("[{}] Connecting to {} through proxy {}", endpoint_host(endpoint), endpoint, proxy.host().unwrap_or_default());
+        let connector = ObservingConnector::new(proxy::ProxyConnector::new(proxy, config), endpoint, true, observed);
+        return Ok(builder.connect_with_connector(connector).await?);
+    }
+    let connector = ObservingConnector::new(connector::http_connector(config), endpoint, false, observed);
+    let channel = builder.connect_with_connector(connector).await?;
+    Ok(channel)
+}
+
+/// Connects a channel, retrying failed attempts as configured in `connect_retries`
+/// 
+/// The delay between attempts follows `connect_backoff`, computed as for stream
+/// reconnects. Invalid endpoints are not retried.
+/// 
+/// # Arguments
+/// * `endpoint` - The gRPC endpoint to connect to
+/// * `config` - Channel settings such as the connect timeout and retries
+/// * `observed` - Where the established connections are recorded
+async fn connect_channel_with_retries(endpoint: &str, config: &ChannelConfig, observed: &ObservedConnections) -> Result<Channel, GrpcStreamError> {
+    let mut reconnect = Reconnect {
+        max_attempts: config.connect_retries,
+        strategy: config.connect_backoff,
+        ..Default::default()
+    };
+    let never = CancellationToken::new();
+
+    loop {
+        match connect_channel(endpoint, config, observed).await {
+            Ok(channel) => return Ok(channel),
+            Err(err) if reconnect.attempts < reconnect.max_attempts && !matches!(err, GrpcStreamError::InvalidEndpoint { .. }) => {
+                log::warn!(
+                    "[{}] Connecting to {} failed: {}, retrying (attempt {} of {})",
+                    endpoint_host(endpoint),
+                    endpoint,
+                    err,
+                    reconnect.attempts + 1,
+                    reconnect.max_attempts
+                );
+                // Below the attempt limit and without a downtime budget, so the wait cannot fail
+                reconnect.wait(&never, endpoint_host(endpoint)).await?;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Connects a channel to an endpoint given as strings
+/// 
+/// # Arguments
+/// * `endpoint` - The gRPC endpoint to connect to
+/// * `x_token` - Optional authentication token sent as `x-token` metadata
+/// * `config` - Channel settings such as the connect timeout
+async fn connect_endpoint(endpoint: &str, x_token: Option<String>, config: &ChannelConfig, observed: &ObservedConnections) -> Result<(Channel, Option<AsciiMetadataValue>), GrpcStreamError> {
+    let x_token = parse_token(x_token)?;
+    let channel = connect_channel(endpoint, config, observed).await?;
+    Ok((channel, x_token))
+}
+
+/// Connects and subscribes to the endpoint a migration moves to
+/// 
+/// The new subscription never requests a snapshot, since the current stream already
+/// delivered it.
+/// 
+/// # Arguments
+/// * `endpoint` - The gRPC endpoint to migrate to
+/// * `x_token` - Optional authentication token for the new endpoint
+/// * `request` - The active subscription request
+/// * `high_water` - The highest slot delivered from the current stream and its updates, the new stream catches up once past it
+/// * `config` - Channel settings such as connect and subscribe timeouts
+/// * `observed` - Where the established connections are recorded
+async fn start_migration(
+    endpoint: &str,
+    x_token: Option<String>,
+    request: &SubscribeRequest,
+    high_water: &HighWaterKeys,
+    config: &ChannelConfig,
+    observed: &ObservedConnections,
+) -> Result<Migration, GrpcStreamError> {
+    let (channel, x_token) = connect_endpoint(endpoint, x_token, config, observed).await?;
+    let mut client = build_client(&channel, x_token.clone(), false, config);
+    let (subscribe_tx, stream) = subscribe(&mut client, request, config.subscribe_timeout).await?;
+    Ok(Migration::new(endpoint.to_string(), channel, x_token, client, subscribe_tx, stream, high_water))
+}
+
+/// Opens a subscription stream
+/// 
+/// With the `otel` feature, the subscribe call carries the `traceparent` of the current
+/// OpenTelemetry context.
+/// 
+/// # Arguments
+/// * `client` - The client to subscribe with
+/// * `request` - The subscription request
+/// * `timeout` - How long to wait for the server to accept the subscription
+async fn subscribe(client: &mut GeyserGrpcClient<InterceptorXToken>, request: &SubscribeRequest, timeout: Option<Duration>) -> Result<(SubscribeSink, UpdateStream), GrpcStreamError> {
+    #[cfg(feature = "otel")]
+    let subscribe = async {
+        let (mut subscribe_tx, subscribe_rx) = futures::channel::mpsc::unbounded();
+        subscribe_tx.send(request.clone()).await?;
+        let mut call = tonic::Request::new(subscribe_rx);
+        otel::inject_trace_context(call.metadata_mut());
+        let response = client.geyser.subscribe(call).await?;
+        Ok::<_, GrpcStreamError>((subscribe_tx, response.into_inner()))
+    };
+    #[cfg(not(feature = "otel"))]
+    let subscribe = client.subscribe_with_request(Some(request.clone()));
+    let (subscribe_tx, stream) = config::with_timeout(timeout, subscribe)
+        .await
+        .map_err(|_| GrpcStreamError::Timeout("subscribe request"))??;
+    Ok((Box::pin(subscribe_tx), Box::pin(stream)))
+}
+
+/// The request answering a server ping
+fn ping_request() -> SubscribeRequest {
+    SubscribeRequest {
+        ping: Some(SubscribeRequestPing { id: 1 }),
+        ..Default::default()
+    }
+}
+
+/// Builds a geyser client on an existing channel
+/// 
+/// # Arguments
+/// * `channel` - The channel to issue requests on
+/// * `x_token` - Optional authentication token sent as `x-token` metadata
+/// * `snapshot` - Whether the server should replay a snapshot of matching accounts on subscribe
+/// * `config` - Channel settings such as the accepted compression
+fn build_client(channel: &Channel, x_token: Option<AsciiMetadataValue>, snapshot: bool, config: &ChannelConfig) -> GeyserGrpcClient<InterceptorXToken> {
+    let interceptor = InterceptorXToken {
+        x_token,
+        x_request_snapshot: snapshot,
+    };
+
+    let mut geyser = GeyserClient::with_interceptor(channel.clone(), interceptor.clone());
+    if let Some(encoding) = config.accept_compression {
+        geyser = geyser.accept_compressed(encoding);
+    }
+
+    GeyserGrpcClient::new(HealthClient::with_interceptor(channel.clone(), interceptor), geyser)
 }
@@ -0,0 +1,46 @@
+use {
+    crate::GrpcStreamError,
+    tonic::{Code, Status},
+    yellowstone_grpc_proto::geyser::SubscribeRequest,
+};
+
+/// Counts the pubkeys referenced by the account filters of a request
+/// 
+/// # Arguments
+/// * `request` - The subscription request to inspect
+pub fn account_filter_count(request: &SubscribeRequest) -> usize {
+    request.accounts.values().map(|filter| filter.account.len() + filter.owner.len()).sum()
+}
+
+/// Checks a request against a client-side account filter limit
+/// 
+/// # Arguments
+/// * `request` - The subscription request to validate
+/// * `limit` - The maximum number of account filter pubkeys, `None` for no limit
+pub(crate) fn check_account_filters(request: &SubscribeRequest, limit: Option<usize>) -> Result<(), GrpcStreamError> {
+    match limit {
+        Some(limit) if account_filter_count(request) > limit => Err(GrpcStreamError::FilterLimitExceeded { limit: Some(limit) }),
+        _ => Ok(()),
+    }
+}
+
+/// Recognizes a server rejection caused by a filter limit
+/// 
+/// Yellowstone rejects oversized filters with `InvalidArgument` and a message such as
+/// "Max amount of Pubkeys reached, only 10000 allowed". The limit is parsed from the
+/// message when present.
+/// 
+/// # Arguments
+/// * `status` - The status returned by the server
+pub(crate) fn filter_limit_rejection(status: &Status) -> Option<GrpcStreamError> {
+    let message = status.message();
+    if status.code() != Code::InvalidArgument || !message.contains("Max amount") {
+        return None;
+    }
+
+    let limit = message
+        .split_once("only ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .and_then(|count| count.parse().ok());
+    Some(GrpcStreamError::FilterLimitExceeded { limit })
+}
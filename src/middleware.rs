@@ -0,0 +1,50 @@
+use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
+
+/// A stage transforming or filtering updates before they reach the handlers
+/// 
+/// Implemented for closures of the same signature.
+pub trait UpdateMiddleware: Send + Sync {
+    /// Processes an update, returning `None` to drop it
+    /// 
+    /// # Arguments
+    /// * `update` - The update received from the stream or the previous stage
+    fn process(&self, update: UpdateOneof) -> Option<UpdateOneof>;
+}
+
+impl<F> UpdateMiddleware for F
+where
+    F: Fn(UpdateOneof) -> Option<UpdateOneof> + Send + Sync,
+{
+    fn process(&self, update: UpdateOneof) -> Option<UpdateOneof> {
+        self(update)
+    }
+}
+
+/// A chain of middleware stages applied in the order they were added
+#[derive(Default)]
+pub struct MiddlewarePipeline {
+    stages: Vec<Box<dyn UpdateMiddleware>>,
+}
+
+impl MiddlewarePipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a stage to the pipeline
+    /// 
+    /// # Arguments
+    /// * `stage` - The middleware receiving the output of the previous stage
+    pub fn with(mut self, stage: impl UpdateMiddleware + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Passes an update through all stages, stopping at the first that drops it
+    /// 
+    /// # Arguments
+    /// * `update` - The update to process
+    pub fn process(&self, update: UpdateOneof) -> Option<UpdateOneof> {
+        self.stages.iter().try_fold(update, |update, stage| stage.process(update))
+    }
+}
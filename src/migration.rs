@@ -0,0 +1,255 @@
+use {
+    crate::{SubscribeSink, UpdateStream},
+    futures::stream::StreamExt,
+    std::collections::{HashMap, HashSet, VecDeque},
+    tonic::{metadata::AsciiMetadataValue, transport::Channel, Status},
+    yellowstone_grpc_client::{GeyserGrpcClient, InterceptorXToken},
+    yellowstone_grpc_proto::geyser::{subscribe_update::UpdateOneof, SubscribeUpdate},
+};
+
+/// Maximum number of updates buffered from the new stream before the cutover
+pub(crate) const MAX_BUFFERED: usize = 100_000;
+
+/// Number of slots around the cutover for which duplicates are filtered
+/// 
+/// Updates delivered from the current stream are remembered for this many slots behind
+/// its highest slot, and filtered on the new stream until this many slots past it.
+const DEDUP_SLOTS: u64 = 32;
+
+/// Returns the slot an update belongs to
+/// 
+/// # Arguments
+/// * `update` - The update to inspect
+pub(crate) fn update_slot(update: &UpdateOneof) -> Option<u64> {
+    match update {
+        UpdateOneof::Account(account) => Some(account.slot),
+        UpdateOneof::Slot(slot) => Some(slot.slot),
+        UpdateOneof::Transaction(tx) => Some(tx.slot),
+        UpdateOneof::TransactionStatus(status) => Some(status.slot),
+        UpdateOneof::Block(block) => Some(block.slot),
+        UpdateOneof::BlockMeta(meta) => Some(meta.slot),
+        UpdateOneof::Entry(entry) => Some(entry.slot),
+        _ => None,
+    }
+}
+
+/// Identifies an update independently of the stream it was received on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum DedupKey {
+    Account(Vec<u8>, u64),
+    Slot(u64, i32),
+    Transaction(Vec<u8>),
+    TransactionStatus(Vec<u8>),
+    Block(u64),
+    BlockMeta(u64),
+    Entry(u64, u64),
+}
+
+impl DedupKey {
+    /// Returns the key of an update, `None` for pings and pongs
+    /// 
+    /// # Arguments
+    /// * `update` - The update to identify
+    pub(crate) fn of(update: &UpdateOneof) -> Option<DedupKey> {
+        match update {
+            UpdateOneof::Account(account) => {
+                let info = account.account.as_ref()?;
+                Some(DedupKey::Account(info.pubkey.clone(), info.write_version))
+            }
+            UpdateOneof::Slot(slot) => Some(DedupKey::Slot(slot.slot, slot.status)),
+            UpdateOneof::Transaction(tx) => Some(DedupKey::Transaction(tx.transaction.as_ref()?.signature.clone())),
+            UpdateOneof::TransactionStatus(status) => Some(DedupKey::TransactionStatus(status.signature.clone())),
+            UpdateOneof::Block(block) => Some(DedupKey::Block(block.slot)),
+            UpdateOneof::BlockMeta(meta) => Some(DedupKey::BlockMeta(meta.slot)),
+            UpdateOneof::Entry(entry) => Some(DedupKey::Entry(entry.slot, entry.index)),
+            _ => None,
+        }
+    }
+}
+
+/// The updates delivered for the highest slot seen so far
+/// 
+/// Kept at all times, so a migration started mid-slot knows which updates of that slot
+/// the current stream already delivered.
+#[derive(Debug, Default)]
+pub(crate) struct HighWaterKeys {
+    slot: u64,
+    keys: HashSet<DedupKey>,
+}
+
+impl HighWaterKeys {
+    /// Records a delivered update, forgetting the previous slot once a higher one arrives
+    /// 
+    /// # Arguments
+    /// * `update` - The update passed on to the handlers
+    pub(crate) fn observe(&mut self, update: &UpdateOneof) {
+        let Some(slot) = update_slot(update) else {
+            return;
+        };
+        if slot > self.slot {
+            self.slot = slot;
+            self.keys.clear();
+        }
+        if slot == self.slot {
+            if let Some(key) = DedupKey::of(update) {
+                self.keys.insert(key);
+            }
+        }
+    }
+}
+
+/// A connection to a new endpoint streaming in parallel until it catches up
+pub(crate) struct Migration {
+    pub(crate) endpoint: String,
+    pub(crate) channel: Channel,
+    pub(crate) x_token: Option<AsciiMetadataValue>,
+    pub(crate) client: GeyserGrpcClient<InterceptorXToken>,
+    pub(crate) subscribe_tx: SubscribeSink,
+    pub(crate) stream: UpdateStream,
+    pub(crate) catch_up: CatchUp,
+}
+
+impl Migration {
+    pub(crate) fn new(endpoint: String, channel: Channel, x_token: Option<AsciiMetadataValue>, client: GeyserGrpcClient<InterceptorXToken>, subscribe_tx: SubscribeSink, stream: UpdateStream, high_water: &HighWaterKeys) -> Self {
+        Migration {
+            endpoint,
+            channel,
+            x_token,
+            client,
+            subscribe_tx,
+            stream,
+            catch_up: CatchUp::new(high_water),
+        }
+    }
+}
+
+/// How far the new stream of a migration got after buffering an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CatchUpState {
+    /// Still behind the current stream
+    Behind,
+    /// Past the highest slot of the current stream, ready for the cutover
+    CaughtUp,
+    /// The buffer is full without the new stream catching up, the update was not buffered
+    Overflowed,
+}
+
+/// Compares the new stream of a migration against the updates delivered from the current one
+pub(crate) struct CatchUp {
+    start_slot: u64,
+    high_water: u64,
+    delivered: HashMap<DedupKey, u64>,
+    buffered: VecDeque<SubscribeUpdate>,
+}
+
+impl CatchUp {
+    /// Starts comparing from the slot the current stream already reached
+    /// 
+    /// The updates already delivered for that slot seed the duplicate filter, the new
+    /// stream replays them in full.
+    /// 
+    /// # Arguments
+    /// * `high_water` - The highest slot delivered from the current stream so far and its updates
+    pub(crate) fn new(high_water: &HighWaterKeys) -> Self {
+        CatchUp {
+            start_slot: high_water.slot,
+            high_water: high_water.slot,
+            delivered: high_water.keys.iter().map(|key| (key.clone(), high_water.slot)).collect(),
+            buffered: VecDeque::new(),
+        }
+    }
+
+    /// Records an update delivered from the current stream
+    /// 
+    /// Updates more than [`DEDUP_SLOTS`] behind the highest slot are forgotten.
+    /// 
+    /// # Arguments
+    /// * `update` - The update passed on to the handlers
+    pub(crate) fn observe(&mut self, update: &UpdateOneof) {
+        let Some(slot) = update_slot(update) else {
+            return;
+        };
+        if slot > self.high_water {
+            self.high_water = slot;
+            let oldest = slot.saturating_sub(DEDUP_SLOTS);
+            self.delivered.retain(|_, delivered_slot| *delivered_slot >= oldest);
+        }
+        if let Some(key) = DedupKey::of(update) {
+            self.delivered.insert(key, slot);
+        }
+    }
+
+    /// Buffers an update from the new stream, returning whether it has caught up
+    /// 
+    /// The new stream has caught up once it delivers an update past the highest slot
+    /// seen on the current stream.
+    /// 
+    /// # Arguments
+    /// * `update` - The update received on the new stream
+    pub(crate) fn buffer(&mut self, update: SubscribeUpdate) -> CatchUpState {
+        if self.buffered.len() == MAX_BUFFERED {
+            return CatchUpState::Overflowed;
+        }
+        let caught_up = update
+            .update_oneof
+            .as_ref()
+            .and_then(update_slot)
+            .is_some_and(|slot| slot > self.high_water);
+
+        self.buffered.push_back(update);
+        if caught_up {
+            CatchUpState::CaughtUp
+        } else {
+            CatchUpState::Behind
+        }
+    }
+
+    /// Splits off the buffered updates and the filter for duplicates after the cutover
+    /// 
+    /// Buffered updates of slots before the one the migration started at were delivered
+    /// from the current stream and are dropped, later ones are left to the filter.
+    pub(crate) fn cutover(&mut self) -> (VecDeque<SubscribeUpdate>, Dedup) {
+        let dedup = Dedup {
+            delivered: std::mem::take(&mut self.delivered),
+            until_slot: self.high_water + DEDUP_SLOTS,
+        };
+        let mut buffered = std::mem::take(&mut self.buffered);
+        buffered.retain(|msg| msg.update_oneof.as_ref().and_then(update_slot).is_none_or(|slot| slot >= self.start_slot));
+        (buffered, dedup)
+    }
+}
+
+/// Filters updates already delivered from the previous stream after a cutover
+pub(crate) struct Dedup {
+    delivered: HashMap<DedupKey, u64>,
+    until_slot: u64,
+}
+
+impl Dedup {
+    /// Whether the update is past the overlap window, making the filter obsolete
+    /// 
+    /// # Arguments
+    /// * `update` - The update received on the new stream
+    pub(crate) fn expired_by(&self, update: &UpdateOneof) -> bool {
+        update_slot(update).is_some_and(|slot| slot > self.until_slot)
+    }
+
+    /// Whether the update was already delivered from the previous stream
+    /// 
+    /// # Arguments
+    /// * `update` - The update received on the new stream
+    pub(crate) fn contains(&self, update: &UpdateOneof) -> bool {
+        DedupKey::of(update).is_some_and(|key| self.delivered.contains_key(&key))
+    }
+}
+
+/// Waits for the next message of a pending migration, never completing if there is none
+/// 
+/// # Arguments
+/// * `migration` - The pending migration, if any
+pub(crate) async fn next(migration: &mut Option<Migration>) -> Option<Result<SubscribeUpdate, Status>> {
+    match migration {
+        Some(migration) => migration.stream.next().await,
+        None => std::future::pending().await,
+    }
+}
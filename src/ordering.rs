@@ -0,0 +1,101 @@
+use {
+    std::collections::HashMap,
+    yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof,
+};
+
+/// The kind of update carried by a `SubscribeUpdate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpdateKind {
+    Slot,
+    Account,
+    Transaction,
+    Block,
+}
+
+impl UpdateKind {
+    /// Returns the kind of an update, `None` for kinds without a dedicated handler
+    /// 
+    /// # Arguments
+    /// * `update` - The update to classify
+    pub fn of(update: &UpdateOneof) -> Option<UpdateKind> {
+        match update {
+            UpdateOneof::Slot(_) => Some(UpdateKind::Slot),
+            UpdateOneof::Account(_) => Some(UpdateKind::Account),
+            UpdateOneof::Transaction(_) => Some(UpdateKind::Transaction),
+            UpdateOneof::Block(_) => Some(UpdateKind::Block),
+            _ => None,
+        }
+    }
+}
+
+/// An update that arrived out of order
+/// 
+/// The sequence value depends on the kind of update: the slot number for slots, the
+/// `write_version` for accounts and the index within the slot for transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderingViolation {
+    pub kind: UpdateKind,
+    /// Slot of the offending update
+    pub slot: u64,
+    /// Sequence value of the last accepted update of this kind
+    pub previous: u64,
+    /// Sequence value of the offending update
+    pub current: u64,
+}
+
+/// Tracks the last sequence value seen per update kind
+/// 
+/// Filtered streams skip most of the chain by design, so only regressions are detected:
+/// a gap in slots, write versions or transaction indexes is indistinguishable from data
+/// that simply did not match the filters. Slot updates are tracked per slot status, since
+/// the same slot is reported once for each status. Snapshot accounts are ignored because
+/// they are replayed in arbitrary order.
+#[derive(Default)]
+pub(crate) struct SequenceTracker {
+    slots: HashMap<i32, u64>,
+    write_version: Option<u64>,
+    transaction: Option<(u64, u64)>,
+}
+
+impl SequenceTracker {
+    /// Records an update, returning a violation if it regresses the sequence
+    /// 
+    /// # Arguments
+    /// * `update` - The update received from the stream
+    pub(crate) fn check(&mut self, update: &UpdateOneof) -> Option<OrderingViolation> {
+        match update {
+            UpdateOneof::Slot(slot) => {
+                let previous = self.slots.insert(slot.status, slot.slot)?;
+                (slot.slot < previous).then(|| OrderingViolation {
+                    kind: UpdateKind::Slot,
+                    slot: slot.slot,
+                    previous,
+                    current: slot.slot,
+                })
+            }
+            UpdateOneof::Account(account) if !account.is_startup => {
+                let current = account.account.as_ref()?.write_version;
+                let previous = self.write_version.replace(current)?;
+                (current <= previous).then(|| OrderingViolation {
+                    kind: UpdateKind::Account,
+                    slot: account.slot,
+                    previous,
+                    current,
+                })
+            }
+            UpdateOneof::Transaction(tx) => {
+                let current = (tx.slot, tx.transaction.as_ref()?.index);
+                match self.transaction.replace(current) {
+                    Some((slot, index)) if slot == current.0 && current.1 <= index => Some(OrderingViolation {
+                        kind: UpdateKind::Transaction,
+                        slot,
+                        previous: index,
+                        current: current.1,
+                    }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
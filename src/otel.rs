@@ -0,0 +1,29 @@
+use {
+    opentelemetry::{trace::TraceContextExt, Context},
+    tonic::metadata::{MetadataMap, MetadataValue},
+};
+
+/// Adds the W3C `traceparent` header for the current OpenTelemetry context
+/// 
+/// Nothing is added if the current context holds no valid span.
+/// 
+/// # Arguments
+/// * `metadata` - The metadata of the outgoing request
+pub(crate) fn inject_trace_context(metadata: &mut MetadataMap) {
+    let context = Context::current();
+    let span = context.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    if let Ok(value) = MetadataValue::try_from(traceparent) {
+        metadata.insert("traceparent", value);
+    }
+}
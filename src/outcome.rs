@@ -0,0 +1,54 @@
+use {
+    std::time::Duration,
+    yellowstone_grpc_proto::geyser::{SubscribeUpdateAccount, SubscribeUpdateTransaction},
+};
+
+/// What an outcome handler asks the manager to do after processing an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerOutcome {
+    /// The update was processed, move on to the next one
+    Continue,
+    /// The update could not be processed, invoke the handler again after a delay
+    Retry,
+    /// Stop the manager, making `connect` return `Ok(())`
+    Stop,
+}
+
+/// What happens to an update whose handler still asks for a retry after the last attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryExhausted {
+    /// Drop the update and continue with the next one
+    Drop,
+    /// Stop the manager
+    Stop,
+}
+
+/// How updates are retried for [`HandlerOutcome::Retry`]
+/// 
+/// Retries happen on the read loop, so no further updates are read from the stream while
+/// an update is waiting to be retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandlerRetry {
+    /// Number of times the handler is invoked again for the same update
+    pub max_retries: u32,
+    /// Delay before the first retry, growing linearly with every further one
+    pub delay: Duration,
+    /// What happens once `max_retries` is used up
+    pub on_exhausted: RetryExhausted,
+}
+
+impl Default for HandlerRetry {
+    fn default() -> Self {
+        HandlerRetry {
+            max_retries: 3,
+            delay: Duration::from_millis(100),
+            on_exhausted: RetryExhausted::Drop,
+        }
+    }
+}
+
+/// An update waiting to be passed to its outcome handler again
+pub(crate) enum PendingRetry {
+    Transaction { tx: SubscribeUpdateTransaction, attempt: u32 },
+    Account { account: SubscribeUpdateAccount, attempt: u32 },
+}
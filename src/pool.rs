@@ -0,0 +1,151 @@
+use {
+    crate::{connect_channel_with_retries, connector::ObservedConnections, parse_token, ChannelConfig, GrpcStreamError, GrpcStreamManager, StreamHandle, StreamStats},
+    std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    tonic::{metadata::AsciiMetadataValue, transport::Channel},
+    yellowstone_grpc_proto::{geyser::SubscribeRequest, prelude::SubscribeUpdateTransaction},
+};
+
+/// The lifecycle state of a pooled subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionState {
+    /// Subscribing or waiting out a reconnect backoff
+    Connecting,
+    /// The subscription is established
+    Connected,
+    /// Cancelled, the stream is being closed
+    Cancelled,
+    /// `connect` returned, either after cancellation or with an error
+    Finished,
+}
+
+/// Cloneable handle to a single subscription of a [`SubscriptionPool`]
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    label: String,
+    stream: StreamHandle,
+    finished: Arc<AtomicBool>,
+}
+
+impl SubscriptionHandle {
+    /// Returns the label the subscription was spawned with
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the current state of the subscription
+    pub fn state(&self) -> SubscriptionState {
+        if self.finished.load(Ordering::Acquire) {
+            SubscriptionState::Finished
+        } else if self.stream.shutdown.is_cancelled() {
+            SubscriptionState::Cancelled
+        } else if self.stream.is_connected() {
+            SubscriptionState::Connected
+        } else {
+            SubscriptionState::Connecting
+        }
+    }
+
+    /// Cancels this subscription, leaving the others sharing the channel running
+    /// 
+    /// Only the subscription's own HTTP/2 stream is closed, the channel stays open for
+    /// as long as other subscriptions or the pool use it.
+    pub fn cancel(&self) {
+        self.stream.shutdown();
+    }
+
+    /// Returns the handle of the underlying manager for further runtime control
+    pub fn stream(&self) -> &StreamHandle {
+        &self.stream
+    }
+}
+
+/// Runs several subscriptions multiplexed over one channel
+/// 
+/// Each subscription is a [`GrpcStreamManager`] with its own handlers, request and
+/// reconnect loop, subscribing as a separate HTTP/2 stream on the shared connection.
+pub struct SubscriptionPool {
+    endpoint: String,
+    x_token: Option<AsciiMetadataValue>,
+    config: ChannelConfig,
+    channel: Channel,
+    observed: ObservedConnections,
+    subscriptions: Vec<SubscriptionHandle>,
+}
+
+impl SubscriptionPool {
+    /// Connects the channel shared by all subscriptions of the pool
+    /// 
+    /// # Arguments
+    /// * `endpoint` - The gRPC endpoint to connect to
+    /// * `x_token` - Optional authentication token sent as `x-token` metadata
+    /// * `config` - Channel settings applied to the shared channel and every subscription
+    pub async fn new(endpoint: &str, x_token: Option<String>, config: ChannelConfig) -> Result<SubscriptionPool, GrpcStreamError> {
+        let x_token = parse_token(x_token)?;
+        let observed = ObservedConnections::default();
+        let channel = connect_channel_with_retries(endpoint, &config, &observed).await?;
+        Ok(SubscriptionPool {
+            endpoint: endpoint.to_string(),
+            x_token,
+            config,
+            channel,
+            observed,
+            subscriptions: Vec::new(),
+        })
+    }
+
+    /// Creates a manager on the shared channel, to be configured and passed to [`spawn`](Self::spawn)
+    /// 
+    /// # Arguments
+    /// * `tx_handler` - Called with every transaction update and the endpoint it was received from
+    pub fn manager(&self, tx_handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>) -> GrpcStreamManager {
+        GrpcStreamManager::from_channel(
+            &self.endpoint,
+            self.x_token.clone(),
+            self.config.clone(),
+            self.channel.clone(),
+            self.observed.clone(),
+            StreamStats::default(),
+            tx_handler,
+        )
+    }
+
+    /// Connects `manager` on a spawned task and adds it to the pool
+    /// 
+    /// The label is also applied to the manager's log lines. Errors ending the
+    /// subscription are logged, the subscription then reports
+    /// [`SubscriptionState::Finished`].
+    /// 
+    /// # Arguments
+    /// * `label` - Identifies the subscription in [`subscriptions`](Self::subscriptions)
+    /// * `manager` - A manager obtained from [`manager`](Self::manager)
+    /// * `request` - The subscription request containing account filters and other parameters
+    pub fn spawn(&mut self, label: &str, mut manager: GrpcStreamManager, request: SubscribeRequest) -> SubscriptionHandle {
+        manager.set_label(Some(label.to_string()));
+        let handle = SubscriptionHandle {
+            label: label.to_string(),
+            stream: manager.handle(),
+            finished: Arc::new(AtomicBool::new(false)),
+        };
+
+        let finished = handle.finished.clone();
+        let label = handle.label.clone();
+        tokio::spawn(async move {
+            if let Err(err) = manager.connect(request).await {
+                log::error!("[{}] Subscription ended: {}", label, err);
+            }
+            finished.store(true, Ordering::Release);
+        });
+
+        self.subscriptions.push(handle.clone());
+        handle
+    }
+
+    /// Returns the subscriptions that have not finished, in the order they were spawned
+    pub fn subscriptions(&mut self) -> Vec<SubscriptionHandle> {
+        self.subscriptions.retain(|subscription| subscription.state() != SubscriptionState::Finished);
+        self.subscriptions.clone()
+    }
+}
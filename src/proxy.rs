@@ -0,0 +1,253 @@
+use {
+    crate::config::ChannelConfig,
+    base64::{prelude::BASE64_STANDARD, Engine},
+    hyper_util::rt::TokioIo,
+    std::{
+        future::Future,
+        io,
+        net::{IpAddr, SocketAddr},
+        pin::Pin,
+        task::{Context, Poll},
+    },
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpSocket, TcpStream},
+    },
+    tokio_socks::tcp::Socks5Stream,
+    tonic::{codegen::http::uri::InvalidUri, transport::Uri},
+};
+
+/// Port assumed for proxy URIs without one, as curl does
+const DEFAULT_PROXY_PORT: u16 = 1080;
+
+/// Upper bound on the size of the proxy's response to a `CONNECT` request
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+/// Returns the proxy to connect to `endpoint` through, `None` to connect directly
+/// 
+/// An explicit `ChannelConfig::proxy` always wins. Otherwise `HTTPS_PROXY` is used for
+/// `https` endpoints and `HTTP_PROXY` for `http` ones, falling back to `ALL_PROXY`, unless
+/// the host is listed in `NO_PROXY`. The lowercase variant of each variable takes
+/// precedence over the uppercase one. Proxies without a scheme default to `http://`.
+/// 
+/// # Arguments
+/// * `endpoint` - The endpoint being connected to
+/// * `config` - Channel settings carrying the explicit proxy
+pub(crate) fn proxy_for(endpoint: &Uri, config: &ChannelConfig) -> Option<Uri> {
+    select_proxy(endpoint, config, |name| std::env::var(name).ok())
+}
+
+/// Returns the proxy to connect to `endpoint` through, reading variables from `env`
+/// 
+/// # Arguments
+/// * `endpoint` - The endpoint being connected to
+/// * `config` - Channel settings carrying the explicit proxy
+/// * `env` - Looks up an environment variable
+pub(crate) fn select_proxy(endpoint: &Uri, config: &ChannelConfig, env: impl Fn(&str) -> Option<String>) -> Option<Uri> {
+    let proxy = match &config.proxy {
+        Some(proxy) if proxy.scheme().is_some() => return Some(proxy.clone()),
+        Some(proxy) => proxy.to_string(),
+        None => {
+            let host = bare_host(endpoint.host()?);
+            if bypasses_proxy(host, env_var("no_proxy", &env)) {
+                return None;
+            }
+            let scheme_var = if endpoint.scheme_str() == Some("https") { "https_proxy" } else { "http_proxy" };
+            [scheme_var, "all_proxy"].into_iter().find_map(|name| env_var(name, &env))?
+        }
+    };
+
+    match parse_proxy(&proxy) {
+        Ok(proxy) => Some(proxy),
+        Err(err) => {
+            log::warn!("[{}] Ignoring invalid proxy {}: {}", endpoint.host().unwrap_or_default(), proxy, err);
+            None
+        }
+    }
+}
+
+/// Parses a proxy URI, defaulting to `http://` without a scheme as curl does
+fn parse_proxy(proxy: &str) -> Result<Uri, InvalidUri> {
+    if proxy.contains("://") {
+        proxy.parse()
+    } else {
+        format!("http://{}", proxy).parse()
+    }
+}
+
+/// Reads a proxy variable, preferring its lowercase name
+fn env_var(name: &str, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    [name.to_string(), name.to_uppercase()]
+        .into_iter()
+        .find_map(|name| env(&name))
+        .filter(|value| !value.is_empty())
+}
+
+/// Whether `no_proxy` lists `host`, either exactly, as a parent domain or through `*`
+/// 
+/// # Arguments
+/// * `host` - The endpoint host without IPv6 brackets
+/// * `no_proxy` - The comma separated value of `NO_PROXY`, if set
+pub(crate) fn bypasses_proxy(host: &str, no_proxy: Option<String>) -> bool {
+    let Some(no_proxy) = no_proxy else {
+        return false;
+    };
+    no_proxy.split(',').map(str::trim).filter(|entry| !entry.is_empty()).any(|entry| {
+        let domain = entry.trim_start_matches('.');
+        entry == "*" || host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+/// Strips the brackets around an IPv6 host
+fn bare_host(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Connector tunneling the channel's TCP connection through an HTTP or SOCKS5 proxy
+/// 
+/// `http://` proxies are asked to open a tunnel with `CONNECT`. `socks5://` proxies get an
+/// address resolved locally, `socks5h://` proxies the host name to resolve themselves.
+/// Credentials are taken verbatim from the proxy URI's user info. TLS to the endpoint is
+/// applied by tonic on top of the tunnel.
+#[derive(Clone)]
+pub(crate) struct ProxyConnector {
+    proxy: Uri,
+    local_address: Option<IpAddr>,
+}
+
+impl ProxyConnector {
+    /// Creates a connector for the given proxy
+    /// 
+    /// # Arguments
+    /// * `proxy` - The proxy URI, see [`proxy_for`]
+    /// * `config` - Channel settings such as the local address
+    pub(crate) fn new(proxy: Uri, config: &ChannelConfig) -> Self {
+        ProxyConnector {
+            proxy,
+            local_address: config.local_address,
+        }
+    }
+
+    /// Opens a tunnel to `target` through the proxy
+    async fn connect(&self, target: Uri) -> io::Result<TcpStream> {
+        let host = target.host().ok_or_else(|| invalid_input("endpoint without host"))?;
+        let default_port = if target.scheme_str() == Some("https") { 443 } else { 80 };
+        let port = target.port_u16().unwrap_or(default_port);
+
+        let proxy_host = self.proxy.host().ok_or_else(|| invalid_input("proxy without host"))?;
+        let proxy_port = self.proxy.port_u16().unwrap_or(DEFAULT_PROXY_PORT);
+        let mut stream = tcp_connect(bare_host(proxy_host), proxy_port, self.local_address).await?;
+
+        match self.proxy.scheme_str() {
+            Some("http") => {
+                http_connect(&mut stream, &format!("{}:{}", host, port), self.credentials()).await?;
+                Ok(stream)
+            }
+            Some("socks5") => {
+                let address = tokio::net::lookup_host((bare_host(host), port))
+                    .await?
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "endpoint did not resolve"))?;
+                self.socks5_connect(stream, address).await
+            }
+            Some("socks5h") => self.socks5_connect(stream, (bare_host(host), port)).await,
+            scheme => Err(invalid_input(&format!("unsupported proxy scheme {:?}", scheme))),
+        }
+    }
+
+    /// Performs the SOCKS5 handshake on a connection to the proxy
+    async fn socks5_connect<'t>(&self, stream: TcpStream, target: impl tokio_socks::IntoTargetAddr<'t>) -> io::Result<TcpStream> {
+        let tunnel = match self.credentials_pair() {
+            Some((user, password)) => Socks5Stream::connect_with_password_and_socket(stream, target, user, password).await,
+            None => Socks5Stream::connect_with_socket(stream, target).await,
+        };
+        tunnel.map(Socks5Stream::into_inner).map_err(io::Error::other)
+    }
+
+    /// Returns the user and password from the proxy URI
+    fn credentials_pair(&self) -> Option<(&str, &str)> {
+        let (userinfo, _) = self.proxy.authority()?.as_str().rsplit_once('@')?;
+        Some(userinfo.split_once(':').unwrap_or((userinfo, "")))
+    }
+
+    /// Returns the proxy credentials encoded for HTTP basic authentication
+    fn credentials(&self) -> Option<String> {
+        self.credentials_pair()
+            .map(|(user, password)| BASE64_STANDARD.encode(format!("{}:{}", user, password)))
+    }
+}
+
+impl tower_service::Service<Uri> for ProxyConnector {
+    type Response = TokioIo<TcpStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: Uri) -> Self::Future {
+        let connector = self.clone();
+        Box::pin(async move { connector.connect(target).await.map(TokioIo::new) })
+    }
+}
+
+/// Connects to the proxy, bound to the local address if one is configured
+async fn tcp_connect(host: &str, port: u16, local_address: Option<IpAddr>) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    let addresses = tokio::net::lookup_host((host, port)).await?;
+    for address in addresses.filter(|address| local_address.is_none_or(|local| local.is_ipv4() == address.is_ipv4())) {
+        let socket = if address.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        if let Some(local) = local_address {
+            socket.bind(SocketAddr::new(local, 0))?;
+        }
+        match socket.connect(address).await {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "proxy did not resolve")))
+}
+
+/// Asks an HTTP proxy to open a tunnel to `authority`
+/// 
+/// # Arguments
+/// * `stream` - The connection to the proxy
+/// * `authority` - The `host:port` to tunnel to
+/// * `credentials` - Basic authentication credentials for the proxy
+async fn http_connect(stream: &mut TcpStream, authority: &str, credentials: Option<String>) -> io::Result<()> {
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+    if let Some(credentials) = credentials {
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing past the response head, i.e. the start of the
+    // tunneled traffic, is consumed
+    let mut head = Vec::new();
+    let mut byte = [0; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "proxy response too large"));
+        }
+        if stream.read(&mut byte).await? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "proxy closed the connection"));
+        }
+        head.push(byte[0]);
+    }
+
+    let status_line = String::from_utf8_lossy(head.split(|byte| *byte == b'\r').next().unwrap_or_default());
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!("proxy refused to tunnel: {}", status_line))),
+    }
+}
+
+/// Builds the error returned for an endpoint or proxy URI that cannot be connected to
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
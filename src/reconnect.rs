@@ -0,0 +1,376 @@
+use {
+    crate::GrpcStreamError,
+    std::{collections::HashMap, error::Error, time::Duration},
+    tokio::time::Instant,
+    tonic::{metadata::AsciiMetadataValue, Code, Status},
+    tokio_util::sync::CancellationToken,
+    yellowstone_grpc_client::GeyserGrpcClientError,
+};
+
+/// What to do once `max_reconnect_attempts` consecutive reconnects have failed
+#[derive(Default)]
+pub enum MaxAttemptsPolicy {
+    /// Return an error from `connect`
+    #[default]
+    Error,
+    /// Sleep for `cooldown`, then reset the attempt counter and keep reconnecting
+    ResetAndContinue { cooldown: Duration },
+    /// Let a callback decide based on the number of failed attempts
+    /// 
+    /// Returning `true` resets the attempt counter and keeps reconnecting, `false` returns
+    /// an error from `connect`.
+    Callback(Box<dyn Fn(u32) -> bool + Send + Sync>),
+}
+
+/// How a stream failing with a given status code is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryDecision {
+    /// Reconnect after the regular backoff
+    Retry,
+    /// Return the error from `connect`
+    Fatal,
+    /// Reconnect after [`LONGER_BACKOFF_FACTOR`] times the regular backoff, e.g. for rate limits
+    RetryWithLongerBackoff,
+}
+
+/// Factor applied to the backoff for [`RetryDecision::RetryWithLongerBackoff`]
+pub const LONGER_BACKOFF_FACTOR: u32 = 4;
+
+/// Returns the retry decisions applied to codes missing from a user-supplied map
+/// 
+/// Authentication failures, invalid requests and unsupported RPCs are fatal since they
+/// fail the same way on every attempt, resource exhaustion is retried with a longer
+/// backoff and everything else is retried.
+pub fn default_retry_policy() -> HashMap<Code, RetryDecision> {
+    HashMap::from([
+        (Code::Unauthenticated, RetryDecision::Fatal),
+        (Code::PermissionDenied, RetryDecision::Fatal),
+        (Code::InvalidArgument, RetryDecision::Fatal),
+        (Code::Unimplemented, RetryDecision::Fatal),
+        (Code::ResourceExhausted, RetryDecision::RetryWithLongerBackoff),
+    ])
+}
+
+/// When reconnects move on to the next endpoint and when they give up altogether
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscalationPolicy {
+    /// Consecutive failed attempts on an endpoint before switching to the next one
+    pub attempts_per_endpoint: u32,
+    /// Full passes through all endpoints without a successful subscribe before `connect` fails
+    pub full_cycles_before_fatal: u32,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        EscalationPolicy {
+            attempts_per_endpoint: 3,
+            full_cycles_before_fatal: 2,
+        }
+    }
+}
+
+/// Rotation of endpoints reconnects escalate through
+pub(crate) struct Escalation {
+    pub(crate) policy: EscalationPolicy,
+    pub(crate) endpoints: Vec<(String, Option<AsciiMetadataValue>)>,
+    pub(crate) index: usize,
+    pub(crate) cycles: u32,
+}
+
+impl Escalation {
+    /// Whether the current endpoint used up its attempts
+    /// 
+    /// # Arguments
+    /// * `attempts` - The consecutive failed attempts on the current endpoint
+    pub(crate) fn due(&self, attempts: u32) -> bool {
+        attempts >= self.policy.attempts_per_endpoint
+    }
+
+    /// Moves on to the next endpoint, failing once all cycles are used up
+    pub(crate) fn advance(&mut self) -> Result<(), GrpcStreamError> {
+        self.index = (self.index + 1) % self.endpoints.len();
+        if self.index == 0 {
+            self.cycles += 1;
+            if self.cycles >= self.policy.full_cycles_before_fatal {
+                return Err(GrpcStreamError::EndpointsExhausted { cycles: self.cycles });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the endpoint and token reconnects currently go to
+    pub(crate) fn current(&self) -> &(String, Option<AsciiMetadataValue>) {
+        &self.endpoints[self.index]
+    }
+}
+
+/// How the delay between reconnect attempts grows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackoffStrategy {
+    /// `interval * attempt`, growing up to `interval * max_multiplier`
+    Linear { interval: Duration, max_multiplier: u32 },
+    /// `initial * 2^(attempt - 1)`, growing up to `max`
+    Exponential { initial: Duration, max: Duration },
+    /// Additive increase, multiplicative decrease
+    /// 
+    /// Every failed attempt adds `step` to the backoff, growing up to `max`. Instead of
+    /// starting over from `initial` after a successful subscribe, the backoff keeps its
+    /// level and is multiplied by `decay`, between `0.0` and `1.0`, for every
+    /// `decay_period` the connection stayed up before failing again, never dropping below
+    /// `initial`. A recovering server thus gets shorter backoffs as it stays up longer.
+    Adaptive { initial: Duration, step: Duration, max: Duration, decay: f64, decay_period: Duration },
+}
+
+impl Default for BackoffStrategy {
+    fn default() -> Self {
+        BackoffStrategy::Linear {
+            interval: Duration::from_secs(5),
+            max_multiplier: 5,
+        }
+    }
+}
+
+impl BackoffStrategy {
+    /// Returns the delay before the given attempt, without jitter
+    /// 
+    /// For [`BackoffStrategy::Adaptive`] this is the delay without any earlier connection,
+    /// the reconnect loop additionally carries the level over between connections.
+    /// 
+    /// # Arguments
+    /// * `attempt` - The 1-based number of the upcoming attempt
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            BackoffStrategy::Linear { interval, max_multiplier } => interval * attempt.min(max_multiplier),
+            BackoffStrategy::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+                initial.saturating_mul(factor).min(max)
+            }
+            BackoffStrategy::Adaptive { initial, step, max, .. } => {
+                initial.saturating_add(step.saturating_mul(attempt.saturating_sub(1))).min(max)
+            }
+        }
+    }
+}
+
+/// Scales a delay by a random factor within `1 ± jitter`
+/// 
+/// # Arguments
+/// * `delay` - The delay to scale
+/// * `jitter` - The maximum relative deviation, between `0.0` and `1.0`
+/// * `sample` - A uniformly distributed value in `[0.0, 1.0)`
+pub fn apply_jitter(delay: Duration, jitter: f64, sample: f64) -> Duration {
+    let jitter = jitter.clamp(0.0, 1.0);
+    delay.mul_f64(1.0 - jitter + 2.0 * jitter * sample)
+}
+
+/// Tracks consecutive reconnect attempts and waits out their backoff
+pub(crate) struct Reconnect {
+    pub(crate) attempts: u32,
+    pub(crate) max_attempts: u32,
+    pub(crate) policy: MaxAttemptsPolicy,
+    pub(crate) strategy: BackoffStrategy,
+    pub(crate) jitter: f64,
+    pub(crate) max_total_downtime: Option<Duration>,
+    pub(crate) down_since: Option<Instant>,
+    pub(crate) retry_policy: HashMap<Code, RetryDecision>,
+    pub(crate) adaptive_level: Option<Duration>,
+    pub(crate) up_since: Option<Instant>,
+    pub(crate) last_backoff: Option<Duration>,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Reconnect {
+            attempts: 0,
+            max_attempts: 10,
+            policy: MaxAttemptsPolicy::default(),
+            strategy: BackoffStrategy::default(),
+            jitter: 0.0,
+            max_total_downtime: None,
+            down_since: None,
+            retry_policy: default_retry_policy(),
+            adaptive_level: None,
+            up_since: None,
+            last_backoff: None,
+        }
+    }
+}
+
+impl Reconnect {
+    /// Whether reconnecting is disabled altogether
+    pub(crate) fn disabled(&self) -> bool {
+        self.max_attempts == 0
+    }
+
+    /// Returns how a stream failing with `code` is handled
+    /// 
+    /// # Arguments
+    /// * `code` - The status code the stream failed with
+    pub(crate) fn decision(&self, code: Code) -> RetryDecision {
+        self.retry_policy.get(&code).copied().unwrap_or(RetryDecision::Retry)
+    }
+
+    /// Returns how a failure of the subscribe RPC or the established stream is handled
+    /// 
+    /// # Arguments
+    /// * `err` - The error the subscribe RPC or the stream failed with
+    pub(crate) fn classify(&self, err: &GrpcStreamError) -> RetryDecision {
+        failure_status(err).map_or(RetryDecision::Retry, |status| self.decision(status.code()))
+    }
+
+    /// Returns `err` if it is not retried, otherwise waits out the backoff it calls for
+    /// 
+    /// # Arguments
+    /// * `err` - The error the subscribe RPC or the stream failed with
+    /// * `shutdown` - Cancels the wait when triggered
+    /// * `label` - Prefixes the log lines of the wait
+    pub(crate) async fn recover(&mut self, err: GrpcStreamError, shutdown: &CancellationToken, label: &str) -> Result<(), GrpcStreamError> {
+        let decision = self.classify(&err);
+        if self.disabled() || decision == RetryDecision::Fatal {
+            return Err(err);
+        }
+        let factor = if decision == RetryDecision::RetryWithLongerBackoff { LONGER_BACKOFF_FACTOR } else { 1 };
+        self.wait_scaled(shutdown, factor, label).await
+    }
+
+    /// Changes the base delay of the backoff strategy
+    /// 
+    /// # Arguments
+    /// * `base` - The new `interval` or `initial` delay
+    pub(crate) fn set_interval(&mut self, base: Duration) {
+        match &mut self.strategy {
+            BackoffStrategy::Linear { interval, .. } => *interval = base,
+            BackoffStrategy::Exponential { initial, .. } | BackoffStrategy::Adaptive { initial, .. } => *initial = base,
+        }
+    }
+
+    /// Resets the attempt counter and downtime after a successful subscribe
+    pub(crate) fn reset(&mut self) {
+        self.attempts = 0;
+        self.down_since = None;
+        self.up_since = Some(Instant::now());
+    }
+
+    /// Returns the backoff before the upcoming attempt, without jitter
+    /// 
+    /// Advances the carried over level of [`BackoffStrategy::Adaptive`], decaying it first
+    /// by the uptime of the connection that just failed.
+    fn next_delay(&mut self) -> Duration {
+        let up_since = self.up_since.take();
+        let BackoffStrategy::Adaptive { initial, step, max, decay, decay_period } = self.strategy else {
+            return self.strategy.delay(self.attempts);
+        };
+
+        let mut delay = self.adaptive_level.unwrap_or(initial);
+        if let Some(up_since) = up_since {
+            let periods = match decay_period.is_zero() {
+                true => f64::INFINITY,
+                false => up_since.elapsed().as_secs_f64() / decay_period.as_secs_f64(),
+            };
+            delay = delay.mul_f64(decay.clamp(0.0, 1.0).powf(periods)).max(initial);
+        }
+        self.adaptive_level = Some(delay.saturating_add(step).min(max));
+        delay
+    }
+
+    /// Waits out the backoff before the next reconnect attempt
+    /// 
+    /// Returns an error if the attempt limit is reached and the [`MaxAttemptsPolicy`]
+    /// decides to give up, or if the backoff would exceed the downtime budget. Returns
+    /// early on shutdown.
+    /// 
+    /// # Arguments
+    /// * `shutdown` - Cancels the wait when triggered
+    /// * `label` - Prefixes the log lines of the wait
+    pub(crate) async fn wait(&mut self, shutdown: &CancellationToken, label: &str) -> Result<(), GrpcStreamError> {
+        self.wait_scaled(shutdown, 1, label).await
+    }
+
+    /// Waits out `factor` times the backoff before the next reconnect attempt, see [`wait`](Self::wait)
+    /// 
+    /// # Arguments
+    /// * `shutdown` - Cancels the wait when triggered
+    /// * `factor` - Multiplies the regular backoff
+    /// * `label` - Prefixes the log lines of the wait
+    pub(crate) async fn wait_scaled(&mut self, shutdown: &CancellationToken, factor: u32, label: &str) -> Result<(), GrpcStreamError> {
+        if self.attempts >= self.max_attempts {
+            let keep_trying = match &self.policy {
+                MaxAttemptsPolicy::Error => false,
+                MaxAttemptsPolicy::ResetAndContinue { cooldown } => {
+                    log::warn!("[{}] Max reconnection attempts reached, retrying in {:?}", label, cooldown);
+                    sleep(*cooldown, shutdown).await;
+                    true
+                }
+                MaxAttemptsPolicy::Callback(decide) => decide(self.attempts),
+            };
+
+            if !keep_trying {
+                return Err(GrpcStreamError::MaxReconnectAttempts(self.attempts));
+            }
+            self.attempts = 0;
+        }
+
+        self.attempts += 1;
+
+        let backoff = apply_jitter(self.next_delay() * factor, self.jitter, rand::random());
+        self.last_backoff = Some(backoff);
+        let down_since = *self.down_since.get_or_insert_with(Instant::now);
+        if let Some(budget) = self.max_total_downtime {
+            let downtime = down_since.elapsed();
+            if downtime + backoff > budget {
+                return Err(GrpcStreamError::MaxDowntime(downtime));
+            }
+        }
+        sleep(backoff, shutdown).await;
+
+        Ok(())
+    }
+}
+
+/// Sleeps for `duration`, returning early on shutdown
+/// 
+/// # Arguments
+/// * `duration` - How long to sleep
+/// * `shutdown` - Cancels the sleep when triggered
+pub(crate) async fn sleep(duration: Duration, shutdown: &CancellationToken) {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {}
+        _ = shutdown.cancelled() => {}
+    }
+}
+
+/// Returns the status returned by the server, whether the subscribe RPC or the stream failed
+/// 
+/// # Arguments
+/// * `err` - The error to inspect
+pub(crate) fn failure_status(err: &GrpcStreamError) -> Option<&Status> {
+    match err {
+        GrpcStreamError::Status(status) | GrpcStreamError::Client(GeyserGrpcClientError::TonicStatus(status)) => Some(status),
+        _ => None,
+    }
+}
+
+/// Number of times in a row a GOAWAY is answered by resubscribing without a backoff
+/// 
+/// Further ones before an update is received reconnect through the backoff like other
+/// stream failures.
+pub(crate) const MAX_GO_AWAY_RESUBSCRIBES: u32 = 3;
+
+/// Whether the stream ended because the server sent an HTTP/2 GOAWAY without an error
+/// 
+/// Servers send such a GOAWAY to drain a connection gracefully, e.g. during deploys, so
+/// the client is expected to reconnect right away. A GOAWAY carrying an error code, such
+/// as `ENHANCE_YOUR_CALM`, is a failure.
+/// 
+/// # Arguments
+/// * `status` - The status the stream failed with
+pub(crate) fn is_graceful_go_away(status: &Status) -> bool {
+    let mut source = status.source();
+    while let Some(err) = source {
+        if err.downcast_ref::<h2::Error>().is_some_and(|err| err.is_go_away() && err.reason() == Some(h2::Reason::NO_ERROR)) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
@@ -0,0 +1,109 @@
+use {
+    prost::Message,
+    prost_types::Timestamp,
+    std::{io::ErrorKind, path::Path, time::Duration},
+    tokio::{
+        fs::File,
+        io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+    },
+    yellowstone_grpc_proto::geyser::SubscribeUpdate,
+};
+
+/// Longest encoded update read back from a recording, far above what servers send, so a
+/// corrupt length prefix fails instead of allocating its length
+pub(crate) const MAX_UPDATE_LEN: usize = 64 * 1024 * 1024;
+
+/// Writes updates to a file, each as a little-endian `u32` length followed by the
+/// protobuf encoded `SubscribeUpdate`
+pub(crate) struct Recorder {
+    file: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Creates the recording file, truncating any existing one
+    /// 
+    /// # Arguments
+    /// * `path` - Where the recording is written
+    pub(crate) async fn create(path: &Path) -> std::io::Result<Self> {
+        let file = File::create(path).await?;
+        Ok(Recorder {
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Appends an update to the recording
+    /// 
+    /// # Arguments
+    /// * `update` - The update to record
+    pub(crate) async fn write(&mut self, update: &SubscribeUpdate) -> std::io::Result<()> {
+        let encoded = update.encode_to_vec();
+        self.file.write_u32_le(encoded.len() as u32).await?;
+        self.file.write_all(&encoded).await
+    }
+
+    /// Flushes buffered updates to the file
+    pub(crate) async fn finish(mut self) -> std::io::Result<()> {
+        self.file.flush().await
+    }
+}
+
+/// Reads updates back from a file written by [`Recorder`]
+pub(crate) struct Replay {
+    file: BufReader<File>,
+    last_created_at: Option<Timestamp>,
+}
+
+impl Replay {
+    /// Opens a recording
+    /// 
+    /// # Arguments
+    /// * `path` - The recording to read
+    pub(crate) async fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path).await?;
+        Ok(Replay {
+            file: BufReader::new(file),
+            last_created_at: None,
+        })
+    }
+
+    /// Reads the next update, `None` at the end of the recording
+    /// 
+    /// Fails with `InvalidData` on a truncated length prefix or a length above [`MAX_UPDATE_LEN`].
+    /// 
+    /// # Arguments
+    /// * `preserve_timing` - Whether to wait out the time between the `created_at` of consecutive updates
+    pub(crate) async fn next(&mut self, preserve_timing: bool) -> std::io::Result<Option<SubscribeUpdate>> {
+        let mut prefix = [0; 4];
+        let mut read = 0;
+        while read < prefix.len() {
+            match self.file.read(&mut prefix[read..]).await? {
+                0 if read == 0 => return Ok(None),
+                0 => return Err(std::io::Error::new(ErrorKind::InvalidData, "truncated length prefix")),
+                n => read += n,
+            }
+        }
+        let len = u32::from_le_bytes(prefix) as usize;
+        if len > MAX_UPDATE_LEN {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, format!("update length {} exceeds {}", len, MAX_UPDATE_LEN)));
+        }
+        let mut encoded = vec![0; len];
+        self.file.read_exact(&mut encoded).await?;
+        let update = SubscribeUpdate::decode(encoded.as_slice()).map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+
+        if preserve_timing {
+            if let (Some(previous), Some(current)) = (&self.last_created_at, &update.created_at) {
+                tokio::time::sleep(elapsed_between(previous, current)).await;
+            }
+        }
+        if update.created_at.is_some() {
+            self.last_created_at = update.created_at;
+        }
+        Ok(Some(update))
+    }
+}
+
+/// Returns the time from `previous` to `current`, zero if `current` is earlier
+fn elapsed_between(previous: &Timestamp, current: &Timestamp) -> Duration {
+    let nanos = (current.seconds - previous.seconds) as i128 * 1_000_000_000 + (current.nanos - previous.nanos) as i128;
+    Duration::from_nanos(nanos.clamp(0, u64::MAX as i128) as u64)
+}
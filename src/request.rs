@@ -0,0 +1,251 @@
+use {
+    crate::GrpcStreamError,
+    yellowstone_grpc_proto::geyser::{
+        CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterBlocks,
+        SubscribeRequestFilterSlots, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+    },
+};
+
+/// Builds a `SubscribeRequest`
+#[derive(Debug, Clone, Default)]
+pub struct SubscribeRequestBuilder {
+    request: SubscribeRequest,
+}
+
+impl SubscribeRequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a named transaction filter
+    /// 
+    /// # Arguments
+    /// * `name` - The filter name, attached by the server to matching updates
+    /// * `filter` - The transaction filter, see [`TransactionFilterBuilder`]
+    pub fn transactions(mut self, name: &str, filter: SubscribeRequestFilterTransactions) -> Self {
+        self.request.transactions.insert(name.to_string(), filter);
+        self
+    }
+
+    /// Adds a named account filter
+    /// 
+    /// # Arguments
+    /// * `name` - The filter name, attached by the server to matching updates
+    /// * `filter` - The account filter
+    pub fn accounts(mut self, name: &str, filter: SubscribeRequestFilterAccounts) -> Self {
+        self.request.accounts.insert(name.to_string(), filter);
+        self
+    }
+
+    /// Adds a transaction filter mirroring a JSON-RPC `logsSubscribe`
+    /// 
+    /// An empty `mentions` corresponds to the `all` filter, which excludes vote
+    /// transactions; otherwise transactions mentioning ANY of the accounts match,
+    /// including votes, like the `mentions` filter. Failed transactions are delivered as
+    /// with RPC. Unlike RPC, `mentions` may hold more than one account, and each update
+    /// carries the full transaction and its meta rather than just the signature, error and
+    /// logs; the logs are in the meta's `log_messages`. Updates use the filter name `logs`.
+    /// 
+    /// # Arguments
+    /// * `mentions` - Base58 encoded account addresses, e.g. `Pubkey`s
+    /// * `commitment` - The commitment level updates are delivered at
+    pub fn logs_subscribe<I>(self, mentions: I, commitment: CommitmentLevel) -> Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        let mut filter = TransactionFilterBuilder::new().include_any_account(mentions);
+        if filter.filter.account_include.is_empty() {
+            filter = filter.vote(false);
+        }
+        self.transactions("logs", filter.build()).commitment(commitment)
+    }
+
+    /// Adds a named block filter
+    /// 
+    /// # Arguments
+    /// * `name` - The filter name, attached by the server to matching updates
+    /// * `filter` - The block filter, see [`BlockFilterBuilder`]
+    pub fn blocks(mut self, name: &str, filter: SubscribeRequestFilterBlocks) -> Self {
+        self.request.blocks.insert(name.to_string(), filter);
+        self
+    }
+
+    /// Adds a named slot filter
+    /// 
+    /// # Arguments
+    /// * `name` - The filter name, attached by the server to matching updates
+    /// * `filter` - The slot filter
+    pub fn slots(mut self, name: &str, filter: SubscribeRequestFilterSlots) -> Self {
+        self.request.slots.insert(name.to_string(), filter);
+        self
+    }
+
+    /// Sets the commitment level updates are delivered at
+    /// 
+    /// # Arguments
+    /// * `commitment` - The commitment level, `Processed` if unset
+    pub fn commitment(mut self, commitment: CommitmentLevel) -> Self {
+        self.request.commitment = Some(commitment as i32);
+        self
+    }
+
+    /// Replays updates starting at `slot`, as far back as the server retains them
+    /// 
+    /// # Arguments
+    /// * `slot` - The first slot to deliver updates for
+    pub fn from_slot(mut self, slot: u64) -> Self {
+        self.request.from_slot = Some(slot);
+        self
+    }
+
+    /// Sends a ping with the initial request to enable server-side liveness checks
+    /// 
+    /// The ping is part of the request, so it is replayed on every reconnect.
+    /// 
+    /// # Arguments
+    /// * `id` - The ping id echoed back by the server in its pong
+    pub fn ping(mut self, id: i32) -> Self {
+        self.request.ping = Some(SubscribeRequestPing { id });
+        self
+    }
+
+    pub fn build(self) -> SubscribeRequest {
+        self.request
+    }
+}
+
+/// Builds a `SubscribeRequestFilterTransactions`
+/// 
+/// The account conditions combine with AND: a transaction matches if it mentions at
+/// least one of the included accounts, all of the required accounts and none of the
+/// excluded accounts. Conditions left empty are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilterBuilder {
+    filter: SubscribeRequestFilterTransactions,
+}
+
+impl TransactionFilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches transactions mentioning ANY of the given accounts (`account_include`)
+    /// 
+    /// # Arguments
+    /// * `accounts` - Base58 encoded account addresses
+    pub fn include_any_account<I>(mut self, accounts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.filter.account_include.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+
+    /// Matches only transactions mentioning ALL of the given accounts (`account_required`)
+    /// 
+    /// # Arguments
+    /// * `accounts` - Base58 encoded account addresses
+    pub fn require_all_accounts<I>(mut self, accounts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.filter.account_required.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+
+    /// Rejects transactions mentioning ANY of the given accounts (`account_exclude`)
+    /// 
+    /// # Arguments
+    /// * `accounts` - Base58 encoded account addresses
+    pub fn exclude_accounts<I>(mut self, accounts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.filter.account_exclude.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+
+    /// Restricts the filter to vote (`true`) or non-vote (`false`) transactions
+    pub fn vote(mut self, vote: bool) -> Self {
+        self.filter.vote = Some(vote);
+        self
+    }
+
+    /// Restricts the filter to failed (`true`) or successful (`false`) transactions
+    pub fn failed(mut self, failed: bool) -> Self {
+        self.filter.failed = Some(failed);
+        self
+    }
+
+    /// Restricts the filter to a single transaction signature
+    pub fn signature(mut self, signature: &str) -> Self {
+        self.filter.signature = Some(signature.to_string());
+        self
+    }
+
+    pub fn build(self) -> SubscribeRequestFilterTransactions {
+        self.filter
+    }
+}
+
+/// Builds a `SubscribeRequestFilterBlocks`
+/// 
+/// Blocks are delivered without their contents unless at least one of the `include_*`
+/// flags is set.
+#[derive(Debug, Clone, Default)]
+pub struct BlockFilterBuilder {
+    filter: SubscribeRequestFilterBlocks,
+}
+
+impl BlockFilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts block contents to entries mentioning ANY of the given accounts (`account_include`)
+    /// 
+    /// # Arguments
+    /// * `accounts` - Base58 encoded account or program addresses
+    pub fn include_any_account<I>(mut self, accounts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.filter.account_include.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+
+    /// Includes the block's transactions
+    pub fn include_transactions(mut self, include: bool) -> Self {
+        self.filter.include_transactions = Some(include);
+        self
+    }
+
+    /// Includes the accounts updated in the block
+    pub fn include_accounts(mut self, include: bool) -> Self {
+        self.filter.include_accounts = Some(include);
+        self
+    }
+
+    /// Includes the block's entries
+    pub fn include_entries(mut self, include: bool) -> Self {
+        self.filter.include_entries = Some(include);
+        self
+    }
+
+    /// Returns the filter, failing if none of the inclusion flags is set
+    /// 
+    /// A filter without inclusion flags only delivers the block header, which is usually
+    /// a mistake; subscribe to block meta updates for that instead.
+    pub fn build(self) -> Result<SubscribeRequestFilterBlocks, GrpcStreamError> {
+        let included = [self.filter.include_transactions, self.filter.include_accounts, self.filter.include_entries];
+        if !included.contains(&Some(true)) {
+            return Err(GrpcStreamError::InvalidFilter("block filter includes no transactions, accounts or entries"));
+        }
+        Ok(self.filter)
+    }
+}
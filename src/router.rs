@@ -0,0 +1,66 @@
+use {
+    std::collections::HashMap,
+    yellowstone_grpc_proto::geyser::{SubscribeRequest, SubscribeRequestFilterTransactions, SubscribeUpdateTransaction},
+};
+
+/// Routes transaction updates to per-filter handlers over a single subscription
+/// 
+/// Each filter is registered under a name, and the server tags every update with the
+/// names of the filters it matched. An update matching several filters is delivered to
+/// each of their handlers.
+#[derive(Default)]
+pub struct TransactionRouter {
+    filters: HashMap<String, SubscribeRequestFilterTransactions>,
+    handlers: HashMap<String, Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>>,
+}
+
+impl TransactionRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a named transaction filter and the handler for its updates
+    /// 
+    /// # Arguments
+    /// * `name` - The filter name, used by the server to tag matching updates
+    /// * `filter` - The transaction filter to subscribe with
+    /// * `handler` - Called with every transaction matching this filter
+    pub fn add_filter(&mut self, name: &str, filter: SubscribeRequestFilterTransactions, handler: Box<dyn Fn(SubscribeUpdateTransaction, &str) + Send + Sync>) -> &mut Self {
+        self.filters.insert(name.to_string(), filter);
+        self.handlers.insert(name.to_string(), handler);
+        self
+    }
+
+    /// Builds a subscription request containing all registered filters
+    pub fn subscribe_request(&self) -> SubscribeRequest {
+        SubscribeRequest {
+            transactions: self.filters.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Dispatches a transaction to the handlers of the filters it matched
+    /// 
+    /// Returns the transaction back if none of the filters belong to this router.
+    /// 
+    /// # Arguments
+    /// * `filters` - The filter names attached to the update
+    /// * `tx` - The transaction update
+    /// * `endpoint` - The endpoint the update was received from
+    pub(crate) fn dispatch(&self, filters: &[String], tx: SubscribeUpdateTransaction, endpoint: &str) -> Option<SubscribeUpdateTransaction> {
+        let mut handlers = filters.iter().filter_map(|name| self.handlers.get(name)).peekable();
+        if handlers.peek().is_none() {
+            return Some(tx);
+        }
+
+        while let Some(handler) = handlers.next() {
+            if handlers.peek().is_some() {
+                handler(tx.clone(), endpoint);
+            } else {
+                handler(tx, endpoint);
+                break;
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,24 @@
+use {
+    crate::{GrpcStreamError, StreamHandle},
+    std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    tokio::task::JoinHandle,
+};
+
+/// Slot subscription returned by [`GrpcStreamManager::track_slots`](crate::GrpcStreamManager::track_slots)
+pub struct SlotTracker {
+    /// Controls the subscription, e.g. to shut it down
+    pub handle: StreamHandle,
+    /// The task running the subscription, completing with the result of `connect`
+    pub task: JoinHandle<Result<(), GrpcStreamError>>,
+    pub(crate) highest: Arc<AtomicU64>,
+}
+
+impl SlotTracker {
+    /// Returns the highest slot received so far, `None` before the first slot update
+    pub fn highest_slot(&self) -> Option<u64> {
+        Some(self.highest.load(Ordering::Relaxed)).filter(|slot| *slot > 0)
+    }
+}
@@ -0,0 +1,46 @@
+use {
+    crate::GrpcStreamError,
+    tokio::{sync::mpsc, task::JoinHandle},
+    yellowstone_grpc_proto::geyser::{
+        subscribe_update::UpdateOneof, SubscribeUpdateAccount, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+    },
+};
+
+/// Typed receivers returned by [`GrpcStreamManager::connect_split`](crate::GrpcStreamManager::connect_split)
+pub struct SplitStreams {
+    pub transactions: mpsc::Receiver<SubscribeUpdateTransaction>,
+    pub accounts: mpsc::Receiver<SubscribeUpdateAccount>,
+    pub slots: mpsc::Receiver<SubscribeUpdateSlot>,
+    /// The task running the subscription, completing with the result of `connect`
+    pub task: JoinHandle<Result<(), GrpcStreamError>>,
+}
+
+/// Sending halves of the typed channels
+pub(crate) struct SplitSenders {
+    pub(crate) transactions: mpsc::Sender<SubscribeUpdateTransaction>,
+    pub(crate) accounts: mpsc::Sender<SubscribeUpdateAccount>,
+    pub(crate) slots: mpsc::Sender<SubscribeUpdateSlot>,
+}
+
+impl SplitSenders {
+    /// Sends an update to the channel of its type, waiting while that channel is full
+    /// 
+    /// Updates of other types, and updates whose receiver was dropped, are discarded.
+    /// 
+    /// # Arguments
+    /// * `update` - The update to send
+    pub(crate) async fn send(&self, update: UpdateOneof) {
+        match update {
+            UpdateOneof::Transaction(tx) => {
+                let _ = self.transactions.send(tx).await;
+            }
+            UpdateOneof::Account(account) => {
+                let _ = self.accounts.send(account).await;
+            }
+            UpdateOneof::Slot(slot) => {
+                let _ = self.slots.send(slot).await;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use {
+    crate::{migration::update_slot, UpdateKind},
+    std::{
+        collections::HashMap,
+        sync::atomic::{AtomicU64, Ordering},
+        time::{Duration, Instant},
+    },
+    yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof,
+};
+
+/// Upper bounds of the [`HandlerHistogram`] buckets, followed by a catch-all bucket
+pub const HANDLER_BUCKETS: [Duration; 5] = [
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+];
+
+/// Statistics collected by the stream manager
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    /// The label of the manager, see `GrpcStreamManager::set_label`
+    pub label: String,
+    /// Time taken to establish the channel
+    /// 
+    /// tonic performs DNS resolution, the TCP connect and the TLS and HTTP/2 handshakes as
+    /// a single step, so these are only available combined.
+    pub channel_connect: Option<Duration>,
+    /// Time taken for the most recent subscribe RPC to be accepted by the server
+    pub last_subscribe: Option<Duration>,
+    /// Time from the most recent subscribe until its first update arrived
+    pub last_first_update: Option<Duration>,
+    /// Handler execution times per update kind, recorded when handler timing is enabled
+    pub handler_timings: HashMap<UpdateKind, HandlerHistogram>,
+    /// Number of async handler invocations currently running
+    pub in_flight_handlers: usize,
+    /// Number of times the subscription was re-established after the first subscribe
+    pub reconnects: u64,
+    /// Highest slot seen in any received update
+    pub highest_slot: Option<u64>,
+    /// The most recent reconnect backoff, including jitter and any longer-backoff factor
+    pub current_backoff: Option<Duration>,
+}
+
+impl StreamStats {
+    /// Raises `highest_slot` to the slot `update` was observed in
+    pub(crate) fn observe_slot(&mut self, update: &UpdateOneof) {
+        self.highest_slot = self.highest_slot.max(update_slot(update));
+    }
+}
+
+/// Distribution of handler execution times
+#[derive(Debug, Clone, Default)]
+pub struct HandlerHistogram {
+    /// Number of invocations per bucket, see [`HANDLER_BUCKETS`]
+    pub buckets: [u64; HANDLER_BUCKETS.len() + 1],
+    /// Total number of invocations
+    pub count: u64,
+    /// Sum of all execution times
+    pub total: Duration,
+    /// Slowest execution time
+    pub max: Duration,
+}
+
+impl HandlerHistogram {
+    /// Returns the mean execution time, `None` if nothing was recorded
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64))
+    }
+
+    pub(crate) fn record(&mut self, elapsed: Duration) {
+        let bucket = HANDLER_BUCKETS.iter().position(|bound| elapsed <= *bound).unwrap_or(HANDLER_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+}
+
+/// When the last update was received, shared between manager and handles
+pub(crate) struct LastMessage {
+    base: Instant,
+    /// Nanoseconds since `base` plus one, `0` if nothing was received yet
+    offset: AtomicU64,
+}
+
+impl Default for LastMessage {
+    fn default() -> Self {
+        LastMessage {
+            base: Instant::now(),
+            offset: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LastMessage {
+    /// Records that an update was received now
+    pub(crate) fn touch(&self) {
+        let offset = self.base.elapsed().as_nanos() as u64 + 1;
+        self.offset.store(offset, Ordering::Relaxed);
+    }
+
+    /// Returns when the last update was received
+    pub(crate) fn at(&self) -> Option<Instant> {
+        match self.offset.load(Ordering::Relaxed) {
+            0 => None,
+            offset => Some(self.base + Duration::from_nanos(offset - 1)),
+        }
+    }
+}
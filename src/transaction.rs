@@ -0,0 +1,112 @@
+use yellowstone_grpc_proto::geyser::SubscribeUpdateTransaction;
+#[cfg(feature = "details")]
+use yellowstone_grpc_proto::prelude::InnerInstructions;
+
+/// Returns whether the transaction executed successfully
+/// 
+/// Transactions without status meta are reported as not succeeded.
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub fn transaction_succeeded(tx: &SubscribeUpdateTransaction) -> bool {
+    tx.transaction
+        .as_ref()
+        .and_then(|info| info.meta.as_ref())
+        .is_some_and(|meta| meta.err.is_none())
+}
+
+/// Returns whether the transaction's status meta carries an error
+/// 
+/// Unlike `!transaction_succeeded`, transactions without status meta are not reported
+/// as failed.
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub(crate) fn transaction_failed(tx: &SubscribeUpdateTransaction) -> bool {
+    tx.transaction
+        .as_ref()
+        .and_then(|info| info.meta.as_ref())
+        .is_some_and(|meta| meta.err.is_some())
+}
+
+/// Returns the raw signature bytes of the transaction without copying
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub fn signature_bytes(tx: &SubscribeUpdateTransaction) -> Option<&[u8]> {
+    tx.transaction.as_ref().map(|info| info.signature.as_slice())
+}
+
+/// Returns the signature of the transaction as a fixed-size key
+/// 
+/// Returns `None` if the transaction info is missing or the signature is not 64 bytes.
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub fn signature_array(tx: &SubscribeUpdateTransaction) -> Option<[u8; 64]> {
+    signature_bytes(tx)?.try_into().ok()
+}
+
+/// Returns the base58 encoded signature of the transaction
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub fn transaction_signature(tx: &SubscribeUpdateTransaction) -> Option<String> {
+    let info = tx.transaction.as_ref()?;
+    Some(bs58::encode(&info.signature).into_string())
+}
+
+/// Returns the slot the transaction was observed in
+/// 
+/// The slot is part of the outer update, next to the transaction info.
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+pub fn transaction_slot(tx: &SubscribeUpdateTransaction) -> u64 {
+    tx.slot
+}
+
+/// Decodes the error a failed transaction returned
+/// 
+/// Returns `None` for successful transactions and for errors that cannot be decoded.
+/// 
+/// # Arguments
+/// * `tx` - The transaction update
+#[cfg(feature = "solana")]
+pub fn transaction_error(tx: &SubscribeUpdateTransaction) -> Option<solana_sdk::transaction::TransactionError> {
+    let err = tx.transaction.as_ref()?.meta.as_ref()?.err.as_ref()?;
+    bincode::deserialize(&err.err).ok()
+}
+
+/// The commonly needed parts of a transaction's status meta
+#[cfg(feature = "details")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionDetails {
+    /// Program log messages, empty if the node did not record them
+    pub logs: Vec<String>,
+    /// Instructions invoked through CPI, grouped by the index of the top-level instruction
+    pub inner_instructions: Vec<InnerInstructions>,
+    /// Compute units consumed, `None` if the node did not report them
+    pub compute_units_consumed: Option<u64>,
+    /// Fee paid in lamports
+    pub fee: u64,
+}
+
+#[cfg(feature = "details")]
+impl TransactionDetails {
+    /// Extracts the details from a transaction update
+    /// 
+    /// Returns `None` for transactions without status meta.
+    /// 
+    /// # Arguments
+    /// * `tx` - The transaction update
+    pub fn from_update(tx: &SubscribeUpdateTransaction) -> Option<Self> {
+        let meta = tx.transaction.as_ref()?.meta.as_ref()?;
+        Some(Self {
+            logs: meta.log_messages.clone(),
+            inner_instructions: meta.inner_instructions.clone(),
+            compute_units_consumed: meta.compute_units_consumed,
+            fee: meta.fee,
+        })
+    }
+}
@@ -0,0 +1,13 @@
+use std::time::Duration;
+
+/// Reconnects when throughput stays below `min_rate` for a whole `window`
+/// 
+/// Pings and pongs do not count towards the rate, so a degraded server that still
+/// answers pings is detected as well.
+#[derive(Debug, Clone, Copy)]
+pub struct ThroughputWatchdog {
+    /// Minimum number of updates per second
+    pub min_rate: f64,
+    /// Period over which the rate is measured
+    pub window: Duration,
+}